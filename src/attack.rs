@@ -1,15 +1,36 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use rand::prelude::*;
-use rand::rng;
+use rand_chacha::ChaCha8Rng;
 
 use crate::{
-    movement::{Destination, MovementOrder},
-    npc::Size,
-    player::Player,
-    schedule::{EditingCatchup, EditingCatchupSet, EditingSet, FreeRoamSet},
+    combat::{AttackRoll, NpcDefence, defence_roll, hit_chance},
+    game_ticks::{GameTickEvent, ProcessingOrder},
+    movement::{
+        Destination, MovementOrder, MovementType, NpcMovementOrders, Speed, moved_position,
+        movement_order,
+    },
+    npc::{Npc, NpcId, Size},
+    player::{Player, PlayerId},
+    rng::SimulationRng,
+    schedule::{
+        EditingCatchup, EditingCatchupSet, EditingSet, FreeRoamSet, Headless, HeadlessSet,
+        PlaybackSet,
+    },
+    world::CollisionMap,
 };
 
+/// Color of the marker drawn on the player when it attacks
+pub const PLAYER_ATTACK_COLOR: Color = Color::srgb(0.2, 0.9, 0.9);
+
+/// Color of the marker drawn on npcs when they attack
+pub const NPC_ATTACK_COLOR: Color = Color::srgb(1., 0.2, 0.2);
+
+/// Colors of the markers drawn on npcs for each style of attack
+pub const NPC_RANGED_ATTACK_COLOR: Color = Color::srgb(0.3, 0.9, 0.3);
+pub const NPC_MAGIC_ATTACK_COLOR: Color = Color::srgb(0.3, 0.5, 1.);
+
 /// What entity is being targeted?
 #[derive(Component, Debug)]
 #[relationship(relationship_target = TargetedBy)]
@@ -21,7 +42,7 @@ pub struct Target(pub Entity);
 pub struct TargetedBy(Vec<Entity>);
 
 /// What to do when your target is underneath you
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TargetUnderBehavior {
     MoveOut,
     RandomCardinal,
@@ -30,94 +51,409 @@ pub enum TargetUnderBehavior {
 
 /// Speed of the entity's attack in game ticks
 #[derive(Component, Debug)]
+#[require(Cooldown)]
 pub struct AttackSpeed(pub u8);
 
 /// Range of the entity's current attack in tiles
 #[derive(Component, Debug)]
 pub struct AttackRange(pub u8);
 
+/// Most damage a single hit of the entity's current attack can do
+#[derive(Component, Debug, Default)]
+pub struct MaxHit(pub u16);
+
+/// Combat style of an attack
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttackStyle {
+    #[default]
+    Melee,
+    Ranged,
+    Magic,
+}
+
+/// What a ranged or magic attack sends at its target, which decides how long it takes to hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProjectileKind {
+    /// Bows and crossbows
+    Arrow,
+    /// Thrown weapons, the blowpipe and chinchompas
+    Thrown,
+    Spell,
+    /// Hits this many game ticks after the attack no matter how far away the target is, for npcs
+    /// whose projectiles don't follow any of the player formulas
+    Fixed(u8),
+}
+
+/// What the entity's current attack sends at its target, nothing for attacks that hit right away
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct AttackProjectile(pub Option<ProjectileKind>);
+
+/// When an npc picks an attack, based on where its target is or on chance
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttackCondition {
+    #[default]
+    Always,
+    /// Target is within melee distance, i.e. cardinally next to the npc
+    Adjacent,
+    /// Target is anywhere but within melee distance
+    NotAdjacent,
+    /// Target is at most this many tiles away
+    Within(u8),
+    /// Target is more than this many tiles away
+    Beyond(u8),
+    /// This percent of the time, rolled anew after every attack the npc makes
+    Chance(u8),
+}
+
+/// A single attack an npc can do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NpcAttack {
+    pub style: AttackStyle,
+    pub range: u8,
+    /// Attack speed in game ticks
+    pub speed: u8,
+    pub condition: AttackCondition,
+    /// Most damage a single hit can do
+    #[serde(default)]
+    pub max_hit: u16,
+    /// What the attack sends at the target if it isn't the usual one for its style
+    #[serde(default)]
+    pub projectile: Option<ProjectileKind>,
+}
+
+/// Attacks of an npc, the first one whose condition holds is the one used
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct NpcAttacks(pub Vec<NpcAttack>);
+
+/// Whether the entity can attack its target from where it is, and why not if it can't
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub enum AttackStatus {
+    InRange,
+    OutOfRange,
+    NoLineOfSight,
+    UnderTarget,
+}
+
+impl std::fmt::Display for AttackStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttackStyle::Melee => write!(f, "Melee"),
+            AttackStyle::Ranged => write!(f, "Ranged"),
+            AttackStyle::Magic => write!(f, "Magic"),
+        }
+    }
+}
+
+impl std::fmt::Display for ProjectileKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProjectileKind::Arrow => write!(f, "Arrow"),
+            ProjectileKind::Thrown => write!(f, "Thrown"),
+            ProjectileKind::Spell => write!(f, "Spell"),
+            ProjectileKind::Fixed(delay) => write!(f, "Fixed +{delay}"),
+        }
+    }
+}
+
+impl AttackStyle {
+    /// What attacks of this style usually send at the target, nothing for melee since it hits
+    /// right away
+    pub fn projectile(&self) -> Option<ProjectileKind> {
+        match self {
+            AttackStyle::Melee => None,
+            AttackStyle::Ranged => Some(ProjectileKind::Arrow),
+            AttackStyle::Magic => Some(ProjectileKind::Spell),
+        }
+    }
+}
+
+impl ProjectileKind {
+    /// Game ticks until the projectile hits a target the given number of tiles away, longer the
+    /// further it has to go
+    pub fn hit_delay(&self, distance: u8) -> u8 {
+        match self {
+            ProjectileKind::Arrow => 1 + (3 + distance) / 6,
+            ProjectileKind::Thrown => 1 + distance / 6,
+            ProjectileKind::Spell => 1 + (1 + distance) / 3,
+            ProjectileKind::Fixed(delay) => *delay,
+        }
+    }
+}
+
+impl NpcAttack {
+    /// What the attack sends at the target, if anything
+    pub fn projectile(&self) -> Option<ProjectileKind> {
+        self.projectile.or(self.style.projectile())
+    }
+}
+
+impl Default for NpcAttack {
+    fn default() -> Self {
+        Self {
+            style: AttackStyle::Melee,
+            range: 1,
+            speed: 4,
+            condition: AttackCondition::Always,
+            max_hit: 0,
+            projectile: None,
+        }
+    }
+}
+
+impl AttackCondition {
+    /// Whether the condition holds with the target at the given distance, chances are rolled
+    /// from the given rng
+    pub fn holds(&self, dist: Vec2, rng: &mut impl Rng) -> bool {
+        let tiles = dist.x.abs().max(dist.y.abs());
+        let adjacent = tiles == 1. && (dist.x == 0. || dist.y == 0.);
+        match self {
+            AttackCondition::Always => true,
+            AttackCondition::Adjacent => adjacent,
+            AttackCondition::NotAdjacent => !adjacent,
+            AttackCondition::Within(range) => tiles <= *range as f32,
+            AttackCondition::Beyond(range) => tiles > *range as f32,
+            AttackCondition::Chance(percent) => rng.random_ratio(u32::from(*percent).min(100), 100),
+        }
+    }
+}
+
+impl std::fmt::Display for AttackCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttackCondition::Always => write!(f, "Always"),
+            AttackCondition::Adjacent => write!(f, "Adjacent"),
+            AttackCondition::NotAdjacent => write!(f, "Not adjacent"),
+            AttackCondition::Within(range) => write!(f, "Within {range} tiles"),
+            AttackCondition::Beyond(range) => write!(f, "Beyond {range} tiles"),
+            AttackCondition::Chance(percent) => write!(f, "{percent}% of the time"),
+        }
+    }
+}
+
+impl std::fmt::Display for TargetUnderBehavior {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TargetUnderBehavior::MoveOut => write!(f, "Move out"),
+            TargetUnderBehavior::RandomCardinal => write!(f, "Random cardinal"),
+            TargetUnderBehavior::StayStill => write!(f, "Stay still"),
+        }
+    }
+}
+
+impl std::fmt::Display for AttackStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttackStatus::InRange => write!(f, "In range"),
+            AttackStatus::OutOfRange => write!(f, "Out of range"),
+            AttackStatus::NoLineOfSight => write!(f, "No line of sight"),
+            AttackStatus::UnderTarget => write!(f, "Under target"),
+        }
+    }
+}
+
 /// Current cooldown until the entity can attack again in game ticks
-#[derive(Component, Debug)]
-struct Cooldown(u8);
+#[derive(Component, Debug, Default)]
+pub struct Cooldown(pub u8);
+
+/// Rng an entity's accuracy and damage rolls come from, forked off the simulation rng when it
+/// first attacks. Every attacker rolling from its own rng keeps changes to one's attacks from
+/// shifting anything else that's random, like where npcs step.
+#[derive(Component, Debug, Clone)]
+pub struct AttackRng(pub ChaCha8Rng);
+
+/// Event sent whenever an entity attacks its target
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct AttackEvent {
+    pub attacker: Entity,
+    pub target: Entity,
+    /// Game ticks until the attack hits, 0 if it hits right away
+    pub hit_delay: u8,
+    /// Damage the attack does once it hits, rolled when the attack is made
+    pub damage: u16,
+    /// Most damage the attack could have done
+    pub max_hit: u16,
+    /// Chance the attack had of getting past the target's defence
+    pub accuracy: f32,
+}
+
+/// Attacks that happened on the most recent game tick
+#[derive(Resource, Debug, Default)]
+pub struct RecentAttacks(pub Vec<AttackEvent>);
 
 pub struct AttackPlugin;
 
 impl Plugin for AttackPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            simultaneous_check.in_set(FreeRoamSet::SimultaneousAttackChecks),
-        )
-        .add_systems(
-            Update,
-            first_check_in_range.in_set(FreeRoamSet::FirstAttackChecks),
-        )
-        .add_systems(
-            Update,
-            second_check_in_range.in_set(FreeRoamSet::SecondAttackChecks),
-        )
-        .add_systems(Update, check_in_range.in_set(EditingSet::AttackChecks))
-        .add_systems(
-            EditingCatchup,
-            simultaneous_check.in_set(EditingCatchupSet::SimultaneousAttackChecks),
-        )
-        .add_systems(
-            EditingCatchup,
-            first_check_in_range.in_set(EditingCatchupSet::FirstAttackChecks),
-        )
-        .add_systems(
-            EditingCatchup,
-            second_check_in_range.in_set(EditingCatchupSet::SecondAttackChecks),
-        );
+        app.insert_resource(RecentAttacks::default())
+            .add_event::<AttackEvent>()
+            .add_systems(
+                Update,
+                (select_npc_attacks, simultaneous_check)
+                    .chain()
+                    .in_set(FreeRoamSet::SimultaneousAttackChecks),
+            )
+            .add_systems(
+                Update,
+                first_check_in_range.in_set(FreeRoamSet::FirstAttackChecks),
+            )
+            .add_systems(
+                Update,
+                second_check_in_range.in_set(FreeRoamSet::SecondAttackChecks),
+            )
+            .add_systems(
+                Update,
+                (select_npc_attacks, check_in_range)
+                    .chain()
+                    .in_set(EditingSet::AttackChecks),
+            )
+            .add_systems(
+                EditingCatchup,
+                (select_npc_attacks, simultaneous_check)
+                    .chain()
+                    .in_set(EditingCatchupSet::SimultaneousAttackChecks),
+            )
+            .add_systems(
+                EditingCatchup,
+                first_check_in_range.in_set(EditingCatchupSet::FirstAttackChecks),
+            )
+            .add_systems(
+                EditingCatchup,
+                second_check_in_range.in_set(EditingCatchupSet::SecondAttackChecks),
+            )
+            .add_systems(
+                Update,
+                (select_npc_attacks, simultaneous_check)
+                    .chain()
+                    .in_set(PlaybackSet::SimultaneousAttackChecks),
+            )
+            .add_systems(
+                Update,
+                first_check_in_range.in_set(PlaybackSet::FirstAttackChecks),
+            )
+            .add_systems(
+                Update,
+                second_check_in_range.in_set(PlaybackSet::SecondAttackChecks),
+            )
+            .add_systems(
+                Headless,
+                (select_npc_attacks, simultaneous_check)
+                    .chain()
+                    .in_set(HeadlessSet::SimultaneousAttackChecks),
+            )
+            .add_systems(
+                Headless,
+                first_check_in_range.in_set(HeadlessSet::FirstAttackChecks),
+            )
+            .add_systems(
+                Headless,
+                second_check_in_range.in_set(HeadlessSet::SecondAttackChecks),
+            )
+            .add_systems(
+                Update,
+                (select_npc_attacks, attack)
+                    .chain()
+                    .run_if(on_event::<GameTickEvent>)
+                    .in_set(FreeRoamSet::Attacks),
+            )
+            .add_systems(
+                EditingCatchup,
+                (select_npc_attacks, attack)
+                    .chain()
+                    .run_if(on_event::<GameTickEvent>)
+                    .in_set(EditingCatchupSet::Attacks),
+            )
+            .add_systems(
+                Update,
+                (select_npc_attacks, attack)
+                    .chain()
+                    .run_if(on_event::<GameTickEvent>)
+                    .in_set(PlaybackSet::Attacks),
+            )
+            .add_systems(
+                Headless,
+                (select_npc_attacks, attack)
+                    .chain()
+                    .run_if(on_event::<GameTickEvent>)
+                    .in_set(HeadlessSet::Attacks),
+            )
+            .add_systems(Update, draw_attacks);
     }
 }
 
+/// Color that attacks of an entity are marked with
+pub fn attack_color(is_player: bool, style: Option<&AttackStyle>) -> Color {
+    match (is_player, style) {
+        (true, _) => PLAYER_ATTACK_COLOR,
+        (false, Some(AttackStyle::Ranged)) => NPC_RANGED_ATTACK_COLOR,
+        (false, Some(AttackStyle::Magic)) => NPC_MAGIC_ATTACK_COLOR,
+        (false, _) => NPC_ATTACK_COLOR,
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn simultaneous_check(
     mut commands: Commands,
-    player_query: Query<Entity, With<Player>>,
-    query: Query<(&Transform, &Target, &AttackRange, &Size), With<Player>>,
+    player_query: Query<(Entity, &Transform, &AttackRange, &Size), With<Player>>,
+    npc_query: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&Target>,
+            &AttackRange,
+            &Size,
+            &Speed,
+            &MovementType,
+        ),
+        With<Npc>,
+    >,
     transforms: Query<&Transform>,
     sizes: Query<&Size>,
+    collision_map: Res<CollisionMap>,
 ) -> Result {
-    // There's a weird special case where the player moves simultaneously with npcs if:
-    // - Their range is 1
-    // - Their dist is (2,2) or one of their x/y dist is 1 and the other is 3
-    //
-    // This actually seems to happen in more cases than just this, but it seems like a pain to
-    // actually figure out all the details of and implement right now so I'm just going to leave it
-    // as it is for now.
-    let entity = player_query.single()?;
-    let Ok((transform, target, range, size)) = query.single() else {
-        // No target, just default to second
-        commands.entity(entity).insert(MovementOrder::Second);
-        return Ok(());
-    };
+    // Npcs are processed before players, so a player normally finds its way to an npc after the
+    // npc has already moved. Each player is ordered against every npc that moves this tick, and
+    // moves in the order it has against the one it's going after.
+    for (player, transform, range, size) in player_query.iter() {
+        let mut orders = Vec::new();
+        for (npc, npc_transform, npc_target, npc_range, npc_size, npc_speed, movement_type) in
+            npc_query.iter()
+        {
+            let Some(npc_target) = npc_target else {
+                continue;
+            };
+            let npc_steps = prv_npc_steps(
+                (
+                    npc_transform.translation.truncate(),
+                    npc_size.0,
+                    npc_range.0,
+                    npc_speed.0,
+                    movement_type,
+                ),
+                (
+                    transforms.get(npc_target.0)?.translation.truncate(),
+                    sizes.get(npc_target.0)?.0,
+                ),
+                &collision_map,
+            );
+            let Some((before_last_step, npc_moved)) = npc_steps else {
+                continue;
+            };
 
-    let target_sw_tile = transforms.get(target.0)?;
-    let target_size = sizes.get(target.0)?;
-
-    let dist = prv_distance_to_entity(
-        transform.translation.truncate(),
-        size.0,
-        target_sw_tile.translation.truncate(),
-        target_size.0,
-    );
-
-    let order = if range.0 == 1
-        && (dist.abs() == Vec2::splat(2.)
-            || dist.abs() == Vec2::new(1., 3.)
-            || dist.abs() == Vec2::new(3., 1.))
-    {
-        MovementOrder::First
-    } else {
-        MovementOrder::Second
-    };
+            let order = prv_movement_order(
+                (transform.translation.truncate(), size.0, range.0),
+                (before_last_step, npc_moved, npc_size.0),
+                &collision_map,
+            );
+            orders.push((npc, order));
+        }
 
-    commands.entity(entity).insert(order);
+        commands.entity(player).insert(NpcMovementOrders(orders));
+    }
 
     Ok(())
 }
 
+#[allow(clippy::type_complexity)]
 fn first_check_in_range(
     mut commands: Commands,
     query: Query<(
@@ -127,25 +463,38 @@ fn first_check_in_range(
         &AttackRange,
         &Size,
         &TargetUnderBehavior,
-        &MovementOrder,
+        (Option<&MovementOrder>, Option<&NpcMovementOrders>),
+        &ProcessingOrder,
     )>,
     transforms: Query<&Transform>,
     sizes: Query<&Size>,
+    collision_map: Res<CollisionMap>,
+    mut rng: ResMut<SimulationRng>,
 ) -> Result {
-    for entry in query.iter() {
-        if entry.6 == &MovementOrder::First {
-            prv_check_in_range(
-                &mut commands,
-                (entry.0, entry.1, entry.2, entry.3, entry.4, entry.5),
-                transforms,
-                sizes,
-            )?;
-        }
+    let mut entries: Vec<_> = query
+        .iter()
+        .filter(|entry| {
+            let (order, npc_orders) = entry.6;
+            movement_order(order, npc_orders, Some(entry.2)) == MovementOrder::First
+        })
+        .collect();
+    entries.sort_by_key(|entry| (*entry.7, entry.0));
+
+    for entry in entries {
+        prv_check_in_range(
+            &mut commands,
+            (entry.0, entry.1, entry.2, entry.3, entry.4, entry.5),
+            transforms,
+            sizes,
+            &collision_map,
+            &mut rng,
+        )?;
     }
 
     Ok(())
 }
 
+#[allow(clippy::type_complexity)]
 fn second_check_in_range(
     mut commands: Commands,
     query: Query<(
@@ -155,20 +504,32 @@ fn second_check_in_range(
         &AttackRange,
         &Size,
         &TargetUnderBehavior,
-        &MovementOrder,
+        (Option<&MovementOrder>, Option<&NpcMovementOrders>),
+        &ProcessingOrder,
     )>,
     transforms: Query<&Transform>,
     sizes: Query<&Size>,
+    collision_map: Res<CollisionMap>,
+    mut rng: ResMut<SimulationRng>,
 ) -> Result {
-    for entry in query.iter() {
-        if entry.6 == &MovementOrder::Second {
-            prv_check_in_range(
-                &mut commands,
-                (entry.0, entry.1, entry.2, entry.3, entry.4, entry.5),
-                transforms,
-                sizes,
-            )?;
-        }
+    let mut entries: Vec<_> = query
+        .iter()
+        .filter(|entry| {
+            let (order, npc_orders) = entry.6;
+            movement_order(order, npc_orders, Some(entry.2)) == MovementOrder::Second
+        })
+        .collect();
+    entries.sort_by_key(|entry| (*entry.7, entry.0));
+
+    for entry in entries {
+        prv_check_in_range(
+            &mut commands,
+            (entry.0, entry.1, entry.2, entry.3, entry.4, entry.5),
+            transforms,
+            sizes,
+            &collision_map,
+            &mut rng,
+        )?;
     }
 
     Ok(())
@@ -183,12 +544,90 @@ fn check_in_range(
         &AttackRange,
         &Size,
         &TargetUnderBehavior,
+        &ProcessingOrder,
+    )>,
+    transforms: Query<&Transform>,
+    sizes: Query<&Size>,
+    collision_map: Res<CollisionMap>,
+    mut rng: ResMut<SimulationRng>,
+) -> Result {
+    let mut entries: Vec<_> = query.iter().collect();
+    entries.sort_by_key(|entry| (*entry.6, entry.0));
+
+    for entry in entries {
+        prv_check_in_range(
+            &mut commands,
+            (entry.0, entry.1, entry.2, entry.3, entry.4, entry.5),
+            transforms,
+            sizes,
+            &collision_map,
+            &mut rng,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::type_complexity)]
+fn select_npc_attacks(
+    mut query: Query<(
+        &NpcAttacks,
+        &Transform,
+        &Size,
+        &Target,
+        &mut AttackRange,
+        &mut AttackSpeed,
+        &mut AttackStyle,
+        &mut MaxHit,
+        &mut AttackProjectile,
+        (Option<&NpcId>, Option<&AttackRng>),
     )>,
     transforms: Query<&Transform>,
     sizes: Query<&Size>,
+    rng: Res<SimulationRng>,
 ) -> Result {
-    for entry in query.iter() {
-        prv_check_in_range(&mut commands, entry, transforms, sizes)?;
+    for (
+        attacks,
+        transform,
+        size,
+        target,
+        mut range,
+        mut speed,
+        mut style,
+        mut max_hit,
+        mut projectile,
+        (npc_id, attack_rng),
+    ) in query.iter_mut()
+    {
+        let target_sw_tile = transforms.get(target.0)?;
+        let target_size = sizes.get(target.0)?;
+        let dist = prv_distance_to_entity(
+            transform.translation.truncate(),
+            size.0,
+            target_sw_tile.translation.truncate(),
+            target_size.0,
+        );
+
+        // Chances are rolled from a copy, so the npc sticks with what it picked until its next
+        // attack moves its rng along. The copy is on a stream of its own so the rolls don't line
+        // up with the ones for damage.
+        let mut selection_rng = prv_attack_rng(&rng, attack_rng, None, npc_id);
+        selection_rng.set_stream(1);
+
+        // Keep using the current attack if none of them apply
+        let Some(attack) = attacks
+            .0
+            .iter()
+            .find(|attack| attack.condition.holds(dist, &mut selection_rng))
+        else {
+            continue;
+        };
+
+        range.0 = attack.range;
+        speed.0 = attack.speed;
+        *style = attack.style;
+        max_hit.0 = attack.max_hit;
+        projectile.0 = attack.projectile();
     }
 
     Ok(())
@@ -206,33 +645,31 @@ fn prv_check_in_range(
     ),
     transforms: Query<&Transform>,
     sizes: Query<&Size>,
+    collision_map: &CollisionMap,
+    rng: &mut SimulationRng,
 ) -> Result {
     let (entity, transform, target, range, size, under_behavior) = entry;
     let target_sw_tile = transforms.get(target.0)?;
     let target_size = sizes.get(target.0)?;
-    let dist = prv_distance_to_entity(
+    let status = prv_attack_status(
         transform.translation.truncate(),
         size.0,
+        range.0,
         target_sw_tile.translation.truncate(),
         target_size.0,
+        collision_map,
     );
 
-    let destination: Option<Vec2>;
-    if dist.x.abs() > range.0 as f32
-        || dist.y.abs() > range.0 as f32
-        // special case for when range is 1
-        || (dist.x.abs() == 1. && dist.y.abs() == 1. && range.0 == 1)
-    {
-        // Out of range, move towards target
-        destination = Some(prv_closest_tile_to_entity(
+    let destination = match status {
+        // Out of range or something is in the way of the projectile, move towards target
+        AttackStatus::OutOfRange | AttackStatus::NoLineOfSight => Some(prv_closest_tile_to_entity(
             transform.translation.truncate(),
             size.0,
             target_sw_tile.translation.truncate(),
             target_size.0,
-        ));
-    } else if dist == Vec2::ZERO {
+        )),
         // under the entity
-        destination = match under_behavior {
+        AttackStatus::UnderTarget => match under_behavior {
             TargetUnderBehavior::MoveOut => Some(prv_closest_tile_to_entity(
                 transform.translation.truncate(),
                 size.0,
@@ -247,10 +684,7 @@ fn prv_check_in_range(
                     Vec2::new(0., -10.),
                     Vec2::new(0., 10.),
                 ];
-                // TODO: In editing catchup the npc moves erratically because it's not deterministic
-                // random. Should probably use bevy_rand.
-                let mut rng = rng();
-                let Some(direction) = directions.choose(&mut rng) else {
+                let Some(direction) = directions.choose(&mut **rng) else {
                     panic!("SHOULD ALWAYS GET DIRECTION");
                 };
                 Some(Vec2::new(
@@ -259,20 +693,283 @@ fn prv_check_in_range(
                 ))
             }
             TargetUnderBehavior::StayStill => None,
-        };
-    } else {
+        },
         // In range! No longer need any destination
-        destination = None;
-    }
+        AttackStatus::InRange => None,
+    };
     if let Some(dest) = destination {
         commands.entity(entity).insert(Destination(dest));
     } else {
         commands.entity(entity).try_remove::<Destination>();
     }
+    commands.entity(entity).insert(status);
 
     Ok(())
 }
 
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn attack(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &Transform,
+        &AttackRange,
+        &AttackSpeed,
+        &Size,
+        &mut Cooldown,
+        Option<&Target>,
+        &ProcessingOrder,
+        (
+            Option<&AttackStyle>,
+            Option<&MaxHit>,
+            Option<&AttackRoll>,
+            Option<&AttackProjectile>,
+        ),
+        (Option<&PlayerId>, Option<&NpcId>, Option<&AttackRng>),
+    )>,
+    transforms: Query<&Transform>,
+    defences: Query<&NpcDefence>,
+    sizes: Query<&Size>,
+    npc_targets: Query<Has<Target>, With<Npc>>,
+    collision_map: Res<CollisionMap>,
+    rng: Res<SimulationRng>,
+    mut recent_attacks: ResMut<RecentAttacks>,
+    mut attack_evw: EventWriter<AttackEvent>,
+) -> Result {
+    recent_attacks.0.clear();
+
+    let mut entries: Vec<_> = query.iter_mut().collect();
+    entries.sort_by_key(|entry| (*entry.7, entry.0));
+
+    // Npcs that started fighting back this tick, which only the first attacker gets to decide
+    let mut retaliating = Vec::new();
+    for (
+        entity,
+        transform,
+        range,
+        speed,
+        size,
+        mut cooldown,
+        target,
+        _,
+        (style, max_hit, attack_roll, projectile),
+        (player_id, npc_id, attack_rng),
+    ) in entries
+    {
+        // The cooldown keeps going down even while not attacking anything
+        cooldown.0 = cooldown.0.saturating_sub(1);
+
+        let Some(target) = target else {
+            continue;
+        };
+
+        // Check again now that everything has moved this tick
+        let target_sw_tile = transforms.get(target.0)?;
+        let target_size = sizes.get(target.0)?;
+        let status = prv_attack_status(
+            transform.translation.truncate(),
+            size.0,
+            range.0,
+            target_sw_tile.translation.truncate(),
+            target_size.0,
+            &collision_map,
+        );
+        commands.entity(entity).insert(status);
+
+        if status != AttackStatus::InRange || cooldown.0 > 0 {
+            continue;
+        }
+
+        cooldown.0 = speed.0;
+        let dist = prv_distance_to_entity(
+            transform.translation.truncate(),
+            size.0,
+            target_sw_tile.translation.truncate(),
+            target_size.0,
+        );
+        let style = style.copied().unwrap_or_default();
+        let max_hit = max_hit.map_or(0, |max_hit| max_hit.0);
+        let mut attack_rng = prv_attack_rng(&rng, attack_rng, player_id, npc_id);
+
+        // Only players have an attack roll and only npcs a defence roll, anything else always
+        // gets past the defence
+        let (accuracy, hits) = match (attack_roll, defences.get(target.0)) {
+            (Some(attack_roll), Ok(defence)) => {
+                let defence_roll = defence_roll(defence, style);
+                (
+                    hit_chance(attack_roll.0, defence_roll),
+                    attack_rng.random_range(0..=attack_roll.0)
+                        > attack_rng.random_range(0..=defence_roll),
+                )
+            }
+            _ => (1., true),
+        };
+        let attack = AttackEvent {
+            attacker: entity,
+            target: target.0,
+            hit_delay: projectile
+                .and_then(|projectile| projectile.0)
+                .map_or(0, |projectile| {
+                    projectile.hit_delay(dist.x.abs().max(dist.y.abs()) as u8)
+                }),
+            damage: if hits {
+                attack_rng.random_range(0..=max_hit)
+            } else {
+                0
+            },
+            max_hit,
+            accuracy,
+        };
+        commands.entity(entity).insert(AttackRng(attack_rng));
+        recent_attacks.0.push(attack);
+        attack_evw.write(attack);
+
+        // Npcs that weren't after anyone fight back
+        if matches!(npc_targets.get(target.0), Ok(false)) && !retaliating.contains(&target.0) {
+            commands.entity(target.0).insert(Target(entity));
+            retaliating.push(target.0);
+        }
+    }
+
+    Ok(())
+}
+
+fn draw_attacks(
+    mut gizmos: Gizmos,
+    recent_attacks: Res<RecentAttacks>,
+    query: Query<(&Transform, &Size, Has<Player>, Option<&AttackStyle>)>,
+) {
+    for attack in recent_attacks.0.iter() {
+        let Ok((transform, size, is_player, style)) = query.get(attack.attacker) else {
+            continue;
+        };
+        let Ok((target_transform, target_size, _, _)) = query.get(attack.target) else {
+            continue;
+        };
+
+        let color = attack_color(is_player, style);
+        let center = transform.translation.truncate() + (size.0 as f32 / 2. - 0.5);
+        let target_center =
+            target_transform.translation.truncate() + (target_size.0 as f32 / 2. - 0.5);
+
+        // Mark the attacker and which way the attack went
+        gizmos.circle_2d(
+            Isometry2d::from_translation(center),
+            size.0 as f32 / 2. - 0.1,
+            color,
+        );
+        gizmos.line_2d(center, target_center, color);
+    }
+}
+
+// Helper to figure out whether an entity can attack its target from where it is
+fn prv_attack_status(
+    start_sw_tile: Vec2,
+    size: u8,
+    range: u8,
+    target_sw_tile: Vec2,
+    target_size: u8,
+    collision_map: &CollisionMap,
+) -> AttackStatus {
+    let dist = prv_distance_to_entity(start_sw_tile, size, target_sw_tile, target_size);
+
+    if dist.x.abs() > range as f32
+        || dist.y.abs() > range as f32
+        // special case for when range is 1
+        || (dist.x.abs() == 1. && dist.y.abs() == 1. && range == 1)
+    {
+        AttackStatus::OutOfRange
+    } else if dist == Vec2::ZERO {
+        AttackStatus::UnderTarget
+    } else if range > 1
+        && !collision_map.has_line_of_sight(start_sw_tile, size, target_sw_tile, target_size)
+    {
+        AttackStatus::NoLineOfSight
+    } else {
+        AttackStatus::InRange
+    }
+}
+
+// Helper to work out where an npc going after its target ends up this tick, along with where it
+// was before its last step, if it moves at all
+fn prv_npc_steps(
+    npc: (Vec2, u8, u8, u8, &MovementType),
+    target: (Vec2, u8),
+    collision_map: &CollisionMap,
+) -> Option<(Vec2, Vec2)> {
+    let (position, size, range, speed, movement_type) = npc;
+    let (target_position, target_size) = target;
+
+    let status = prv_attack_status(
+        position,
+        size,
+        range,
+        target_position,
+        target_size,
+        collision_map,
+    );
+    if !matches!(
+        status,
+        AttackStatus::OutOfRange | AttackStatus::NoLineOfSight
+    ) {
+        return None;
+    }
+
+    let destination = prv_closest_tile_to_entity(position, size, target_position, target_size);
+    let moved = |speed| {
+        moved_position(
+            position,
+            destination,
+            speed,
+            size,
+            movement_type,
+            collision_map,
+        )
+    };
+
+    Some((moved(speed.saturating_sub(1)), moved(speed)))
+}
+
+// Helper to decide whether a player moves along with (`First`) or after (`Second`) an npc, given
+// where the npc was before its last step this tick and where it ends up.
+//
+// The player moves along with the npc when the npc's last step is diagonal and leaves the player
+// a single straight step away from being able to attack it, the player then takes that step
+// instead of following where the npc ends up. With range 1 and a size 1 npc walking towards the
+// player this happens at distances of (2, 2), (1, 3) and (3, 1). Only the npc's last step counts,
+// so its speed just changes where that step starts, and its size where the player has to be.
+fn prv_movement_order(
+    player: (Vec2, u8, u8),
+    npc: (Vec2, Vec2, u8),
+    collision_map: &CollisionMap,
+) -> MovementOrder {
+    let (position, size, range) = player;
+    let (before_last_step, npc_moved, npc_size) = npc;
+
+    let last_step = npc_moved - before_last_step;
+    if last_step.x == 0. || last_step.y == 0. {
+        return MovementOrder::Second;
+    }
+
+    let status_from =
+        |from| prv_attack_status(from, size, range, npc_moved, npc_size, collision_map);
+    if status_from(position) == AttackStatus::InRange {
+        return MovementOrder::Second;
+    }
+
+    let destination = prv_closest_tile_to_entity(position, size, npc_moved, npc_size);
+    let step = (destination - position).clamp(Vec2::NEG_ONE, Vec2::ONE);
+    if (step.x == 0.) == (step.y == 0.) || !collision_map.can_step(position, size, step) {
+        return MovementOrder::Second;
+    }
+
+    if status_from(position + step) == AttackStatus::InRange {
+        MovementOrder::First
+    } else {
+        MovementOrder::Second
+    }
+}
+
 // Helper to calculate distance to a target entity.
 fn prv_distance_to_entity(
     start_sw_tile: Vec2,
@@ -404,3 +1101,248 @@ fn prv_closest_tile_to_entity(
 
     dest_tile
 }
+
+// Helper to get the rng an entity's attacks roll from, forked off the simulation rng if it
+// hasn't attacked yet
+fn prv_attack_rng(
+    rng: &SimulationRng,
+    attack_rng: Option<&AttackRng>,
+    player_id: Option<&PlayerId>,
+    npc_id: Option<&NpcId>,
+) -> ChaCha8Rng {
+    match attack_rng {
+        Some(attack_rng) => attack_rng.0.clone(),
+        None => {
+            // Players and npcs are numbered separately, so the key says which one it is too
+            let (kind, id) = match (player_id, npc_id) {
+                (Some(id), _) => (0, id.0),
+                (None, Some(id)) => (1, id.0),
+                (None, None) => (2, 0),
+            };
+            rng.fork(&[[kind].as_slice(), &id.to_le_bytes()].concat())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    // Helper to decide the order of a size 1 player at the origin going after an npc that starts on
+    // the given tile and walks towards the player to attack it in melee
+    fn prv_order(range: u8, npc: Vec2, npc_size: u8, npc_speed: u8) -> MovementOrder {
+        let collision_map = CollisionMap::default();
+        let Some((before_last_step, npc_moved)) = prv_npc_steps(
+            (npc, npc_size, 1, npc_speed, &MovementType::Dumb),
+            (Vec2::ZERO, 1),
+            &collision_map,
+        ) else {
+            return MovementOrder::Second;
+        };
+
+        prv_movement_order(
+            (Vec2::ZERO, 1, range),
+            (before_last_step, npc_moved, npc_size),
+            &collision_map,
+        )
+    }
+
+    #[test]
+    fn hit_delays() {
+        let delays = |kind: ProjectileKind| [1, 2, 3, 5, 6, 9, 10].map(|d| kind.hit_delay(d));
+        assert_eq!(delays(ProjectileKind::Arrow), [1, 1, 2, 2, 2, 3, 3]);
+        assert_eq!(delays(ProjectileKind::Thrown), [1, 1, 1, 1, 2, 2, 2]);
+        assert_eq!(delays(ProjectileKind::Spell), [1, 2, 2, 3, 3, 4, 4]);
+        assert_eq!(delays(ProjectileKind::Fixed(4)), [4; 7]);
+    }
+
+    #[test]
+    fn moves_along_with_npc_in_known_cases() {
+        for npc in [Vec2::new(2., 2.), Vec2::new(1., 3.), Vec2::new(3., 1.)] {
+            assert_eq!(
+                prv_order(1, npc, 1, 1),
+                MovementOrder::First,
+                "npc at {npc}"
+            );
+        }
+    }
+
+    #[test]
+    fn moves_along_with_npc_from_every_side() {
+        for npc in [
+            Vec2::new(-2., 2.),
+            Vec2::new(-2., -2.),
+            Vec2::new(2., -2.),
+            Vec2::new(-1., -3.),
+            Vec2::new(-3., 1.),
+        ] {
+            assert_eq!(
+                prv_order(1, npc, 1, 1),
+                MovementOrder::First,
+                "npc at {npc}"
+            );
+        }
+    }
+
+    #[test]
+    fn moves_after_npc_otherwise() {
+        for npc in [
+            // Last step is straight
+            Vec2::new(3., 0.),
+            Vec2::new(0., 3.),
+            // Ends up next to the player
+            Vec2::new(2., 1.),
+            // Still more than a step away
+            Vec2::new(3., 3.),
+            Vec2::new(2., 4.),
+            // Already next to the player, so it doesn't move
+            Vec2::new(1., 0.),
+        ] {
+            assert_eq!(
+                prv_order(1, npc, 1, 1),
+                MovementOrder::Second,
+                "npc at {npc}"
+            );
+        }
+    }
+
+    #[test]
+    fn longer_ranges() {
+        // One straight step brings the npc into range
+        assert_eq!(prv_order(7, Vec2::new(1., 9.), 1, 1), MovementOrder::First);
+        assert_eq!(prv_order(4, Vec2::new(6., 1.), 1, 1), MovementOrder::First);
+        // Already in range of where the npc ends up
+        assert_eq!(prv_order(7, Vec2::new(2., 2.), 1, 1), MovementOrder::Second);
+        // Would take a diagonal step to get in range
+        assert_eq!(prv_order(7, Vec2::new(9., 9.), 1, 1), MovementOrder::Second);
+    }
+
+    #[test]
+    fn npc_sizes() {
+        // A size 3 npc lines up with the player where a size 1 npc on the same tile doesn't
+        assert_eq!(prv_order(1, Vec2::new(-2., 3.), 3, 1), MovementOrder::First);
+        assert_eq!(
+            prv_order(1, Vec2::new(-2., 3.), 1, 1),
+            MovementOrder::Second
+        );
+        assert_eq!(
+            prv_order(1, Vec2::new(-3., -3.), 3, 1),
+            MovementOrder::Second
+        );
+    }
+
+    #[test]
+    fn npc_speeds() {
+        // Only the last of the npc's steps counts
+        assert_eq!(prv_order(1, Vec2::new(3., 3.), 1, 2), MovementOrder::First);
+        assert_eq!(prv_order(1, Vec2::new(3., 3.), 1, 1), MovementOrder::Second);
+        assert_eq!(prv_order(1, Vec2::new(2., 4.), 1, 2), MovementOrder::First);
+    }
+
+    #[test]
+    fn ordered_against_every_moving_npc() {
+        let mut world = World::new();
+        world.insert_resource(CollisionMap::default());
+        let player = world
+            .spawn((Player, Transform::default(), AttackRange(1), Size(1)))
+            .id();
+        let mut spawn_npc = |position: Vec2, target: Option<Entity>| {
+            let mut npc = world.spawn((
+                Npc {
+                    name: String::from("test"),
+                },
+                Transform::from_translation(position.extend(0.)),
+                AttackRange(1),
+                Size(1),
+                Speed(1),
+                MovementType::Dumb,
+            ));
+            if let Some(target) = target {
+                npc.insert(Target(target));
+            }
+            npc.id()
+        };
+        let diagonal = spawn_npc(Vec2::new(2., 2.), Some(player));
+        let other_diagonal = spawn_npc(Vec2::new(-3., 1.), Some(player));
+        let straight = spawn_npc(Vec2::new(3., 0.), Some(player));
+        let waiting = spawn_npc(Vec2::new(1., 3.), None);
+        world.entity_mut(player).insert(Target(diagonal));
+
+        world
+            .run_system_once(simultaneous_check)
+            .expect("SYSTEM SHOULD RUN")
+            .expect("SYSTEM SHOULD SUCCEED");
+
+        let orders = world
+            .get::<NpcMovementOrders>(player)
+            .expect("PLAYER SHOULD HAVE ORDERS");
+        assert_eq!(orders.get(diagonal), Some(&MovementOrder::First));
+        assert_eq!(orders.get(other_diagonal), Some(&MovementOrder::First));
+        assert_eq!(orders.get(straight), Some(&MovementOrder::Second));
+        // Isn't going anywhere, so there's nothing to be ordered against
+        assert_eq!(orders.get(waiting), None);
+    }
+
+    #[test]
+    fn first_attack_that_applies_is_picked() {
+        let attacks = vec![
+            NpcAttack {
+                style: AttackStyle::Melee,
+                condition: AttackCondition::Adjacent,
+                ..default()
+            },
+            NpcAttack {
+                style: AttackStyle::Ranged,
+                range: 5,
+                condition: AttackCondition::Within(5),
+                ..default()
+            },
+            NpcAttack {
+                style: AttackStyle::Magic,
+                range: 10,
+                condition: AttackCondition::Always,
+                ..default()
+            },
+        ];
+
+        for (position, style, range) in [
+            (Vec2::new(1., 0.), AttackStyle::Melee, 1),
+            // Diagonal isn't adjacent, but it's within 5
+            (Vec2::new(1., 1.), AttackStyle::Ranged, 5),
+            (Vec2::new(5., 0.), AttackStyle::Ranged, 5),
+            (Vec2::new(6., 0.), AttackStyle::Magic, 10),
+        ] {
+            let mut world = World::new();
+            world.insert_resource(SimulationRng::default());
+            let player = world
+                .spawn((Transform::from_translation(position.extend(0.)), Size(1)))
+                .id();
+            let npc = world
+                .spawn((
+                    NpcAttacks(attacks.clone()),
+                    Transform::default(),
+                    Size(1),
+                    Target(player),
+                    AttackRange(1),
+                    AttackSpeed(4),
+                    AttackStyle::Melee,
+                    MaxHit(0),
+                    AttackProjectile(None),
+                ))
+                .id();
+
+            world
+                .run_system_once(select_npc_attacks)
+                .expect("SYSTEM SHOULD RUN")
+                .expect("SYSTEM SHOULD SUCCEED");
+
+            assert_eq!(world.get::<AttackStyle>(npc), Some(&style), "{position}");
+            assert_eq!(
+                world.get::<AttackRange>(npc).map(|range| range.0),
+                Some(range)
+            );
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::attack::AttackStyle;
+use crate::gear::{GearSetup, WeaponStyle};
+
+/// Combat levels of a player, boosted or not
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CombatStats {
+    pub attack: u8,
+    pub strength: u8,
+    pub ranged: u8,
+    pub magic: u8,
+}
+
+/// Equipment bonuses of a gear setup, only the ones for the style it attacks with
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GearBonuses {
+    /// Stab, slash or crush bonus for melee, ranged attack or magic attack
+    pub attack: i16,
+    /// Melee strength, ranged strength or magic damage in percent
+    pub strength: i16,
+    /// Max hit of the spell before any bonuses, only used by magic
+    pub spell_max_hit: u16,
+}
+
+/// Defensive stats of an npc
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NpcDefence {
+    pub defence_level: u16,
+    pub magic_level: u16,
+    /// Defence bonus against the type of melee attack it's hit with
+    pub melee_bonus: i16,
+    pub ranged_bonus: i16,
+    pub magic_bonus: i16,
+}
+
+/// Roll a player's attack is checked against the target's defence roll to decide whether it hits
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct AttackRoll(pub u32);
+
+impl Default for CombatStats {
+    fn default() -> Self {
+        Self {
+            attack: 99,
+            strength: 99,
+            ranged: 99,
+            magic: 99,
+        }
+    }
+}
+
+impl Default for NpcDefence {
+    fn default() -> Self {
+        Self {
+            defence_level: 1,
+            magic_level: 1,
+            melee_bonus: 0,
+            ranged_bonus: 0,
+            magic_bonus: 0,
+        }
+    }
+}
+
+/// Most damage a single hit with the gear setup can do
+pub fn max_hit(stats: &CombatStats, setup: &GearSetup) -> u16 {
+    let (_, strength_boost) = prv_style_boosts(setup);
+    let strength_bonus = setup.bonuses.strength as i32;
+
+    let max_hit = match setup.style {
+        AttackStyle::Melee | AttackStyle::Ranged => {
+            let level = match setup.style {
+                AttackStyle::Melee => stats.strength,
+                _ => stats.ranged,
+            };
+            let effective = level as i32 + strength_boost + 8;
+            (effective * (strength_bonus + 64) + 320) / 640
+        }
+        AttackStyle::Magic => setup.bonuses.spell_max_hit as i32 * (100 + strength_bonus) / 100,
+    };
+
+    max_hit.max(0) as u16
+}
+
+/// Attack roll of a player with the gear setup
+pub fn attack_roll(stats: &CombatStats, setup: &GearSetup) -> u32 {
+    let (attack_boost, _) = prv_style_boosts(setup);
+    let level = match setup.style {
+        AttackStyle::Melee => stats.attack,
+        AttackStyle::Ranged => stats.ranged,
+        AttackStyle::Magic => stats.magic,
+    };
+    // Magic gets one more invisible level than the other styles
+    let invisible = match setup.style {
+        AttackStyle::Magic => 9,
+        _ => 8,
+    };
+    let effective = level as i32 + attack_boost + invisible;
+
+    (effective * (setup.bonuses.attack as i32 + 64)).max(0) as u32
+}
+
+/// Defence roll of an npc against attacks of the given style
+pub fn defence_roll(defence: &NpcDefence, style: AttackStyle) -> u32 {
+    let (level, bonus) = match style {
+        AttackStyle::Melee => (defence.defence_level, defence.melee_bonus),
+        AttackStyle::Ranged => (defence.defence_level, defence.ranged_bonus),
+        AttackStyle::Magic => (defence.magic_level, defence.magic_bonus),
+    };
+
+    ((level as i32 + 9) * (bonus as i32 + 64)).max(0) as u32
+}
+
+/// Chance of an attack roll beating a defence roll, when both are rolled evenly from 0 up to them
+pub fn hit_chance(attack_roll: u32, defence_roll: u32) -> f32 {
+    let (attack_roll, defence_roll) = (attack_roll as f32, defence_roll as f32);
+    if attack_roll > defence_roll {
+        1. - (defence_roll + 2.) / (2. * (attack_roll + 1.))
+    } else {
+        attack_roll / (2. * (defence_roll + 1.))
+    }
+}
+
+/// Average damage of an attack, any hit doing evenly anywhere from 0 up to the max hit
+pub fn expected_damage(hit_chance: f32, max_hit: u16) -> f32 {
+    hit_chance * max_hit as f32 / 2.
+}
+
+// Helper to get the invisible attack and strength level boosts from the weapon style in use
+fn prv_style_boosts(setup: &GearSetup) -> (i32, i32) {
+    let Some(pick) = &setup.weapon else {
+        return (0, 0);
+    };
+
+    match (setup.style, pick.style) {
+        // Accurate ranged boosts both, which is what makes it worth using over rapid
+        (AttackStyle::Ranged, WeaponStyle::Accurate) => (3, 3),
+        (_, WeaponStyle::Accurate) => (3, 0),
+        (_, WeaponStyle::Aggressive) => (0, 3),
+        (_, WeaponStyle::Controlled) => (1, 1),
+        _ => (0, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gear::{MAX_ATTACK_RANGE, Weapon, WeaponPick};
+
+    // Helper to make a setup attacking with the style and bonuses, without a weapon style boost
+    fn prv_setup(style: AttackStyle, bonuses: GearBonuses) -> GearSetup {
+        GearSetup {
+            style,
+            bonuses,
+            ..GearSetup::unarmed()
+        }
+    }
+
+    #[test]
+    fn melee_and_ranged_max_hits() {
+        let stats = CombatStats::default();
+        assert_eq!(max_hit(&stats, &GearSetup::unarmed()), 11);
+
+        let bonuses = GearBonuses {
+            strength: 80,
+            ..default()
+        };
+        let setup = prv_setup(AttackStyle::Ranged, bonuses);
+        assert_eq!(max_hit(&stats, &setup), 24);
+        // Accurate ranged's invisible strength boost
+        let setup = GearSetup {
+            weapon: Some(WeaponPick {
+                weapon: String::from("Bow"),
+                style: WeaponStyle::Accurate,
+            }),
+            ..setup
+        };
+        assert_eq!(max_hit(&stats, &setup), 25);
+
+        let low_stats = CombatStats {
+            strength: 1,
+            ..default()
+        };
+        assert_eq!(max_hit(&low_stats, &GearSetup::unarmed()), 1);
+    }
+
+    #[test]
+    fn magic_max_hits() {
+        let stats = CombatStats::default();
+        let bonuses = GearBonuses {
+            strength: 10,
+            spell_max_hit: 30,
+            ..default()
+        };
+        assert_eq!(max_hit(&stats, &prv_setup(AttackStyle::Magic, bonuses)), 33);
+
+        // Magic level doesn't matter, only the spell does
+        let low_stats = CombatStats {
+            magic: 1,
+            ..default()
+        };
+        let bonuses = GearBonuses {
+            spell_max_hit: 30,
+            ..default()
+        };
+        assert_eq!(
+            max_hit(&low_stats, &prv_setup(AttackStyle::Magic, bonuses)),
+            30
+        );
+    }
+
+    #[test]
+    fn attack_and_defence_rolls() {
+        let stats = CombatStats::default();
+        assert_eq!(attack_roll(&stats, &GearSetup::unarmed()), 107 * 64);
+
+        let bonuses = GearBonuses {
+            attack: 100,
+            ..default()
+        };
+        assert_eq!(
+            attack_roll(&stats, &prv_setup(AttackStyle::Magic, bonuses)),
+            108 * 164
+        );
+
+        let defence = NpcDefence {
+            defence_level: 100,
+            magic_level: 50,
+            melee_bonus: 20,
+            ranged_bonus: -64,
+            magic_bonus: 0,
+        };
+        assert_eq!(defence_roll(&defence, AttackStyle::Melee), 109 * 84);
+        assert_eq!(defence_roll(&defence, AttackStyle::Ranged), 0);
+        assert_eq!(defence_roll(&defence, AttackStyle::Magic), 59 * 64);
+    }
+
+    #[test]
+    fn hit_chances() {
+        assert_eq!(hit_chance(0, 100), 0.);
+        assert_eq!(hit_chance(100, 200), 100. / 402.);
+        assert_eq!(hit_chance(200, 100), 1. - 102. / 402.);
+        assert_eq!(hit_chance(100, 100), 100. / 202.);
+        assert_eq!(expected_damage(0.5, 20), 5.);
+    }
+
+    #[test]
+    fn rapid_and_longrange() {
+        let bow = Weapon {
+            name: String::from("Bow"),
+            attack_style: AttackStyle::Ranged,
+            speed: 4,
+            range: 7,
+            projectile: None,
+            styles: vec![
+                WeaponStyle::Accurate,
+                WeaponStyle::Rapid,
+                WeaponStyle::Longrange,
+            ],
+        };
+        let mut setup = GearSetup::unarmed();
+
+        setup.pick_weapon(&bow, WeaponStyle::Accurate);
+        assert_eq!((setup.speed, setup.range), (4, 7));
+        setup.pick_weapon(&bow, WeaponStyle::Rapid);
+        assert_eq!((setup.speed, setup.range), (3, 7));
+        setup.pick_weapon(&bow, WeaponStyle::Longrange);
+        assert_eq!((setup.speed, setup.range), (4, 9));
+
+        // Rapid can't make anything attack every 0 ticks
+        let fast_bow = Weapon {
+            speed: 1,
+            ..bow.clone()
+        };
+        setup.pick_weapon(&fast_bow, WeaponStyle::Rapid);
+        assert_eq!(setup.speed, 1);
+
+        // Longrange never goes past the furthest anything can attack from
+        let long_bow = Weapon { range: 9, ..bow };
+        setup.pick_weapon(&long_bow, WeaponStyle::Longrange);
+        assert_eq!(setup.range, MAX_ATTACK_RANGE);
+        assert_eq!(MAX_ATTACK_RANGE, 10);
+    }
+}
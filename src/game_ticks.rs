@@ -1,6 +1,10 @@
 use bevy::prelude::*;
 
-use crate::schedule::{EditingCatchup, EditingCatchupSet, FreeRoamSet};
+use crate::playback::{Playback, PlaybackRestartEvent, PlaybackStepEvent};
+use crate::schedule::{
+    EditingCatchup, EditingCatchupSet, FreeRoamSet, Headless, HeadlessSet, PlaybackSet,
+};
+use crate::state::ToolState;
 
 const GAME_TICK_SECONDS: f32 = 0.6;
 
@@ -8,6 +12,14 @@ const GAME_TICK_SECONDS: f32 = 0.6;
 #[derive(Event, Debug)]
 pub struct GameTickEvent;
 
+/// Order entities are processed in on every game tick. Every npc is processed before any player,
+/// npcs by their index and players by their PID, lowest first.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProcessingOrder {
+    Npc(u16),
+    Player(u16),
+}
+
 #[derive(Resource, Debug, Default)]
 struct GameTickTimer {
     timer: Timer,
@@ -25,7 +37,16 @@ impl Plugin for GameTickPlugin {
         .add_systems(
             EditingCatchup,
             game_tick_update.in_set(EditingCatchupSet::GameTick),
-        );
+        )
+        .add_systems(Headless, game_tick_update.in_set(HeadlessSet::GameTick))
+        .add_systems(OnEnter(ToolState::Playback), reset_game_tick_timer)
+        .add_systems(
+            Update,
+            reset_game_tick_timer
+                .run_if(on_event::<PlaybackRestartEvent>)
+                .in_set(PlaybackSet::Restart),
+        )
+        .add_systems(Update, game_tick_playback.in_set(PlaybackSet::GameTick));
     }
 }
 
@@ -41,6 +62,33 @@ fn game_tick_real_time(
     }
 }
 
+fn game_tick_playback(
+    mut tick_timer: ResMut<GameTickTimer>,
+    time: Res<Time>,
+    mut playback: ResMut<Playback>,
+    mut step_evr: EventReader<PlaybackStepEvent>,
+    mut game_tick_evw: EventWriter<GameTickEvent>,
+) {
+    let step = step_evr.read().count() > 0;
+    if playback.finished() {
+        playback.playing = false;
+        return;
+    }
+
+    if playback.playing {
+        tick_timer.timer.tick(time.delta().mul_f32(playback.speed));
+        if tick_timer.timer.finished() {
+            game_tick_evw.write(GameTickEvent);
+        }
+    } else if step {
+        game_tick_evw.write(GameTickEvent);
+    }
+}
+
+fn reset_game_tick_timer(mut tick_timer: ResMut<GameTickTimer>) {
+    tick_timer.timer.reset();
+}
+
 fn game_tick_update(mut game_tick_evw: EventWriter<GameTickEvent>) {
     game_tick_evw.write(GameTickEvent);
 }
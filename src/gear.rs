@@ -0,0 +1,215 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::attack::{
+    AttackProjectile, AttackRange, AttackSpeed, AttackStyle, MaxHit, ProjectileKind,
+};
+use crate::combat::{AttackRoll, CombatStats, GearBonuses, attack_roll, max_hit};
+use crate::scenario::Scenario;
+
+/// Name of what the player has on when no gear setup is picked
+const UNARMED_NAME: &str = "Unarmed";
+
+/// Furthest any attack can reach in tiles, even with longrange
+pub const MAX_ATTACK_RANGE: u8 = 10;
+
+/// Weapons bundled with the app to pick gear from
+const WEAPON_DATA: &str = include_str!("../assets/weapons.ron");
+
+/// A named set of equipment the player can switch to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GearSetup {
+    pub name: String,
+    /// Weapon and style the stats come from, if they weren't entered by hand
+    #[serde(default)]
+    pub weapon: Option<WeaponPick>,
+    pub style: AttackStyle,
+    /// Attack speed in game ticks
+    pub speed: u8,
+    pub range: u8,
+    /// What the setup attacks with if it isn't the usual one for its style
+    #[serde(default)]
+    pub projectile: Option<ProjectileKind>,
+    #[serde(default)]
+    pub bonuses: GearBonuses,
+}
+
+/// A weapon from the weapon data along with the style it's used with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeaponPick {
+    pub weapon: String,
+    pub style: WeaponStyle,
+}
+
+/// Style picked in the combat options, some of which change the weapon's speed or range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeaponStyle {
+    Accurate,
+    Aggressive,
+    Controlled,
+    Defensive,
+    /// Attacks a tick faster
+    Rapid,
+    /// Attacks from 2 tiles further away
+    Longrange,
+}
+
+/// A weapon as listed in the weapon data
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Weapon {
+    pub name: String,
+    pub attack_style: AttackStyle,
+    /// Attack speed in game ticks before any style changes it
+    pub speed: u8,
+    /// Range in tiles before any style changes it
+    pub range: u8,
+    /// What the weapon attacks with if it isn't the usual one for its style
+    #[serde(default)]
+    pub projectile: Option<ProjectileKind>,
+    /// Styles the weapon can be used with, the first one is the default
+    pub styles: Vec<WeaponStyle>,
+}
+
+/// Every weapon gear can be picked from
+#[derive(Resource, Debug, Clone)]
+pub struct WeaponData(pub Vec<Weapon>);
+
+/// Every gear setup of a method, shared by all of the players
+#[derive(Resource, Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GearSetups {
+    pub setups: Vec<GearSetup>,
+}
+
+/// Name of the gear setup the player currently has on
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Equipped(pub String);
+
+impl GearSetup {
+    /// What the player fights with without any gear
+    pub fn unarmed() -> Self {
+        Self {
+            name: String::from(UNARMED_NAME),
+            weapon: None,
+            style: AttackStyle::Melee,
+            speed: 4,
+            range: 1,
+            projectile: None,
+            bonuses: GearBonuses::default(),
+        }
+    }
+
+    /// Switch to using the weapon with the given style, taking its stats
+    pub fn pick_weapon(&mut self, weapon: &Weapon, style: WeaponStyle) {
+        self.weapon = Some(WeaponPick {
+            weapon: weapon.name.clone(),
+            style,
+        });
+        self.style = weapon.attack_style;
+        self.speed = weapon.speed_with(style);
+        self.range = weapon.range_with(style);
+        self.projectile = weapon.projectile;
+    }
+
+    /// What the setup sends at the target, if anything
+    pub fn projectile(&self) -> Option<ProjectileKind> {
+        self.projectile.or(self.style.projectile())
+    }
+
+    /// Components a player with the given stats gets from having this setup on
+    pub fn bundle(&self, stats: &CombatStats) -> impl Bundle {
+        (
+            AttackSpeed(self.speed),
+            AttackRange(self.range),
+            MaxHit(max_hit(stats, self)),
+            AttackRoll(attack_roll(stats, self)),
+            AttackProjectile(self.projectile()),
+            self.style,
+            Equipped(self.name.clone()),
+        )
+    }
+}
+
+impl GearSetups {
+    /// Setup with the given name
+    pub fn get(&self, name: &str) -> Option<&GearSetup> {
+        self.setups.iter().find(|setup| setup.name == name)
+    }
+
+    /// Setup with the given name, or no gear at all without one
+    pub fn get_or_unarmed(&self, name: Option<&str>) -> GearSetup {
+        name.and_then(|name| self.get(name))
+            .cloned()
+            .unwrap_or_else(GearSetup::unarmed)
+    }
+
+    /// Name for a new setup that isn't taken by any of the others yet
+    pub fn unused_name(&self) -> String {
+        (1..)
+            .map(|i| format!("gear {i}"))
+            .find(|name| self.get(name).is_none())
+            .expect("SHOULD ALWAYS FIND AN UNUSED NAME")
+    }
+}
+
+impl Weapon {
+    /// Attack speed when used with the given style
+    pub fn speed_with(&self, style: WeaponStyle) -> u8 {
+        match style {
+            WeaponStyle::Rapid => self.speed.saturating_sub(1).max(1),
+            _ => self.speed,
+        }
+    }
+
+    /// Attack range when used with the given style
+    pub fn range_with(&self, style: WeaponStyle) -> u8 {
+        match style {
+            WeaponStyle::Longrange => u8::min(self.range + 2, MAX_ATTACK_RANGE),
+            _ => self.range,
+        }
+    }
+}
+
+impl WeaponData {
+    /// The weapon data bundled with the app
+    pub fn bundled() -> Self {
+        Self(ron::from_str(WEAPON_DATA).expect("BUNDLED WEAPON DATA SHOULD BE VALID"))
+    }
+
+    /// Weapon with the given name
+    pub fn get(&self, name: &str) -> Option<&Weapon> {
+        self.0.iter().find(|weapon| weapon.name == name)
+    }
+}
+
+impl std::fmt::Display for WeaponStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WeaponStyle::Accurate => write!(f, "Accurate"),
+            WeaponStyle::Aggressive => write!(f, "Aggressive"),
+            WeaponStyle::Controlled => write!(f, "Controlled"),
+            WeaponStyle::Defensive => write!(f, "Defensive"),
+            WeaponStyle::Rapid => write!(f, "Rapid"),
+            WeaponStyle::Longrange => write!(f, "Longrange"),
+        }
+    }
+}
+
+pub struct GearPlugin;
+
+impl Plugin for GearPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GearSetups::default())
+            .insert_resource(WeaponData::bundled())
+            .add_systems(
+                PreUpdate,
+                sync_gear_setups.run_if(resource_changed::<Scenario>),
+            );
+    }
+}
+
+fn sync_gear_setups(scenario: Res<Scenario>, mut gear_setups: ResMut<GearSetups>) {
+    // Done before anything runs in Update so that re-running the sequence uses the new setups
+    if *gear_setups != scenario.gear {
+        *gear_setups = scenario.gear.clone();
+    }
+}
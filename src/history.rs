@@ -0,0 +1,275 @@
+use bevy::prelude::*;
+
+use crate::scenario::Scenario;
+use crate::schedule::EditingSet;
+use crate::sequence::{ActionSequence, SequenceLane};
+use crate::snapshot::SequenceSnapshots;
+use crate::state::EditingState;
+
+/// Most edits kept around to undo
+const MAX_HISTORY_LEN: usize = 100;
+
+/// Everything about the method that can be edited, as it was at some point
+#[derive(Debug, Clone)]
+struct MethodState {
+    lanes: Vec<SequenceLane>,
+    /// Not an edit itself, but brings the tick being edited back into view
+    target_tick: usize,
+    scenario: Scenario,
+}
+
+/// Past and undone states of the method being edited
+#[derive(Resource, Debug, Default)]
+pub struct EditHistory {
+    undo: Vec<MethodState>,
+    redo: Vec<MethodState>,
+    /// State as of the last recorded edit
+    current: Option<MethodState>,
+    /// Something was edited but hasn't been recorded yet, e.g. because a drag isn't over
+    pending: bool,
+}
+
+/// Event to move through the edit history
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub enum HistoryEvent {
+    Undo,
+    Redo,
+}
+
+/// Event to record the sequence as it is now as an edit that can be undone, scenario edits don't
+/// need one since nothing but the user changes the scenario
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct RecordEditEvent;
+
+impl MethodState {
+    fn same_edits(&self, other: &MethodState) -> bool {
+        self.lanes == other.lanes && self.scenario == other.scenario
+    }
+}
+
+impl EditHistory {
+    /// Forget everything, e.g. when a different method is opened
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(EditHistory::default())
+            .add_event::<HistoryEvent>()
+            .add_event::<RecordEditEvent>()
+            .add_systems(
+                Update,
+                (move_through_history, record_history)
+                    .chain()
+                    .in_set(EditingSet::History),
+            );
+    }
+}
+
+fn record_history(
+    mut record_edit_evr: EventReader<RecordEditEvent>,
+    mut history: ResMut<EditHistory>,
+    action_sequence: Res<ActionSequence>,
+    scenario: Res<Scenario>,
+    mouse: Res<ButtonInput<MouseButton>>,
+) {
+    if !record_edit_evr.is_empty() || scenario.is_changed() {
+        record_edit_evr.clear();
+        history.pending = true;
+    }
+
+    if !history.pending
+        && let Some(current) = history.current.as_mut()
+    {
+        // Catchup replacing redundant inputs and moving between ticks aren't edits of their own,
+        // but undoing back to here should still end up with them
+        if action_sequence.is_changed() {
+            current.lanes.clone_from(&action_sequence.lanes);
+            current.target_tick = action_sequence.target_tick;
+        }
+        return;
+    }
+
+    // Wait until a drag is over so it ends up as a single edit
+    if mouse.pressed(MouseButton::Left) {
+        return;
+    }
+    history.pending = false;
+
+    let state = MethodState {
+        lanes: action_sequence.lanes.clone(),
+        target_tick: action_sequence.target_tick,
+        scenario: scenario.clone(),
+    };
+    let Some(current) = history.current.take() else {
+        history.current = Some(state);
+        return;
+    };
+    if current.same_edits(&state) {
+        // Only moved to a different tick, which isn't worth undoing
+        history.current = Some(state);
+        return;
+    }
+
+    history.undo.push(current);
+    if history.undo.len() > MAX_HISTORY_LEN {
+        history.undo.remove(0);
+    }
+    history.redo.clear();
+    history.current = Some(state);
+}
+
+fn move_through_history(
+    mut history_evr: EventReader<HistoryEvent>,
+    mut history: ResMut<EditHistory>,
+    mut action_sequence: ResMut<ActionSequence>,
+    mut scenario: ResMut<Scenario>,
+    mut snapshots: ResMut<SequenceSnapshots>,
+    mut next_state: ResMut<NextState<EditingState>>,
+) {
+    for event in history_evr.read() {
+        let history = &mut *history;
+        let Some(current) = history.current.take() else {
+            continue;
+        };
+        let (from, to) = match event {
+            HistoryEvent::Undo => (&mut history.undo, &mut history.redo),
+            HistoryEvent::Redo => (&mut history.redo, &mut history.undo),
+        };
+        let Some(state) = from.pop() else {
+            history.current = Some(current);
+            continue;
+        };
+        to.push(current);
+
+        // Only the ticks from the first changed one on have to be re-run
+        let first_changed = action_sequence
+            .lanes
+            .iter()
+            .zip(state.lanes.iter())
+            .map(|(old, new)| {
+                old.ticks
+                    .iter()
+                    .zip(new.ticks.iter())
+                    .position(|(old, new)| old != new)
+                    .unwrap_or(usize::min(old.ticks.len(), new.ticks.len()))
+            })
+            .min()
+            .unwrap_or_default();
+        snapshots.invalidate_after(first_changed);
+
+        action_sequence.lanes = state.lanes.clone();
+        action_sequence.target_tick = usize::min(state.target_tick, action_sequence.len() - 1);
+        if action_sequence
+            .lane(action_sequence.selected_player)
+            .is_none()
+        {
+            action_sequence.selected_player = state.lanes[0].player;
+        }
+        if *scenario != state.scenario {
+            // Different setup means nothing that's been run still holds
+            *scenario = state.scenario.clone();
+            snapshots.clear();
+        }
+        history.current = Some(state);
+
+        next_state.set(EditingState::Reconciliation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::{PlayerAction, PlayerId};
+
+    #[test]
+    fn undo_keeps_catchup_rewrites() {
+        let (mut world, mut schedule) = prv_world();
+        schedule.run(&mut world);
+        let start = prv_lanes(&world);
+
+        // An edit, then catchup replacing one of its inputs
+        prv_set_input(&mut world, 1, PlayerAction::Move(Vec2::new(3., 3.)));
+        prv_set_input(&mut world, 0, PlayerAction::Move(Vec2::new(3., 3.)));
+        world.send_event(RecordEditEvent);
+        schedule.run(&mut world);
+        prv_set_input(&mut world, 1, PlayerAction::Idle);
+        schedule.run(&mut world);
+        let rewritten = prv_lanes(&world);
+        assert_eq!(world.resource::<EditHistory>().undo.len(), 1);
+
+        world.send_event(HistoryEvent::Undo);
+        schedule.run(&mut world);
+        assert_eq!(prv_lanes(&world), start);
+        assert!(matches!(
+            world.resource::<NextState<EditingState>>(),
+            NextState::Pending(EditingState::Reconciliation)
+        ));
+
+        // Comes back with the rewrite instead of the input that was replaced
+        world.send_event(HistoryEvent::Redo);
+        schedule.run(&mut world);
+        assert_eq!(prv_lanes(&world), rewritten);
+
+        // Nothing left to redo
+        world.send_event(HistoryEvent::Redo);
+        schedule.run(&mut world);
+        assert_eq!(prv_lanes(&world), rewritten);
+        assert_eq!(world.resource::<EditHistory>().undo.len(), 1);
+    }
+
+    #[test]
+    fn new_edits_clear_redo() {
+        let (mut world, mut schedule) = prv_world();
+        schedule.run(&mut world);
+
+        prv_set_input(&mut world, 0, PlayerAction::Move(Vec2::new(1., 1.)));
+        world.send_event(RecordEditEvent);
+        schedule.run(&mut world);
+        world.send_event(HistoryEvent::Undo);
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<EditHistory>().redo.len(), 1);
+
+        prv_set_input(&mut world, 0, PlayerAction::Move(Vec2::new(2., 2.)));
+        world.send_event(RecordEditEvent);
+        schedule.run(&mut world);
+        let history = world.resource::<EditHistory>();
+        assert!(history.redo.is_empty());
+        assert_eq!(history.undo.len(), 1);
+    }
+
+    // Helper to make a world with a two tick sequence, along with the history systems in the order
+    // they're run in
+    fn prv_world() -> (World, Schedule) {
+        let mut world = World::new();
+        let mut action_sequence = ActionSequence::default();
+        action_sequence.lanes[0] = SequenceLane::idle(PlayerId(0), 2);
+        world.insert_resource(action_sequence);
+        world.insert_resource(Scenario::default());
+        world.insert_resource(EditHistory::default());
+        world.insert_resource(SequenceSnapshots::default());
+        world.insert_resource(ButtonInput::<MouseButton>::default());
+        world.init_resource::<NextState<EditingState>>();
+        world.init_resource::<Events<HistoryEvent>>();
+        world.init_resource::<Events<RecordEditEvent>>();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((move_through_history, record_history).chain());
+
+        (world, schedule)
+    }
+
+    // Helper to replace the inputs of a tick
+    fn prv_set_input(world: &mut World, tick: usize, action: PlayerAction) {
+        world.resource_mut::<ActionSequence>().lanes[0].ticks[tick].0 = vec![action];
+    }
+
+    // Helper to get the lanes as they are now
+    fn prv_lanes(world: &World) -> Vec<SequenceLane> {
+        world.resource::<ActionSequence>().lanes.clone()
+    }
+}
@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+
+use crate::{
+    attack::Target,
+    game_ticks::GameTickEvent,
+    movement::Destination,
+    npc::{Npc, Size},
+    projectile::RecentHits,
+    schedule::{
+        EditingCatchup, EditingCatchupSet, FreeRoamSet, Headless, HeadlessSet, PlaybackSet,
+    },
+};
+
+/// Color of the part of a health bar that's left
+const HEALTH_BAR_COLOR: Color = Color::srgb(0., 0.8, 0.);
+
+/// Color of the part of a health bar that's been lost
+const HEALTH_BAR_LOST_COLOR: Color = Color::srgb(0.8, 0., 0.);
+
+/// Hitpoints an entity has left, out of what it started with
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Hitpoints {
+    pub current: u16,
+    pub max: u16,
+}
+
+/// Marks an npc whose hitpoints ran out. It stays around so it can come back when the sequence is
+/// run again, but doesn't do anything and can't be attacked anymore.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Dead;
+
+/// Npcs that died on the most recent game tick
+#[derive(Resource, Debug, Default)]
+pub struct RecentDeaths(pub Vec<Entity>);
+
+impl Hitpoints {
+    /// Full hitpoints
+    pub fn new(max: u16) -> Self {
+        Self { current: max, max }
+    }
+}
+
+pub struct HitpointsPlugin;
+
+impl Plugin for HitpointsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RecentDeaths::default())
+            .add_systems(
+                Update,
+                apply_hits
+                    .run_if(on_event::<GameTickEvent>)
+                    .in_set(FreeRoamSet::Damage),
+            )
+            .add_systems(
+                EditingCatchup,
+                apply_hits
+                    .run_if(on_event::<GameTickEvent>)
+                    .in_set(EditingCatchupSet::Damage),
+            )
+            .add_systems(
+                Update,
+                apply_hits
+                    .run_if(on_event::<GameTickEvent>)
+                    .in_set(PlaybackSet::Damage),
+            )
+            .add_systems(
+                Headless,
+                apply_hits
+                    .run_if(on_event::<GameTickEvent>)
+                    .in_set(HeadlessSet::Damage),
+            )
+            .add_systems(Update, (draw_health_bars, hide_dead_npcs));
+    }
+}
+
+fn apply_hits(
+    mut commands: Commands,
+    recent_hits: Res<RecentHits>,
+    mut recent_deaths: ResMut<RecentDeaths>,
+    mut hitpoints_query: Query<(&mut Hitpoints, Has<Npc>), Without<Dead>>,
+    target_query: Query<(Entity, &Target)>,
+) {
+    recent_deaths.0.clear();
+
+    for hit in recent_hits.0.iter() {
+        // Anything that already died doesn't take any more damage
+        let Ok((mut hitpoints, is_npc)) = hitpoints_query.get_mut(hit.target) else {
+            continue;
+        };
+        if hitpoints.current == 0 {
+            continue;
+        }
+
+        hitpoints.current = hitpoints.current.saturating_sub(hit.damage);
+
+        // Players keep going so the rest of the method can still be seen
+        if hitpoints.current > 0 || !is_npc {
+            continue;
+        }
+
+        commands
+            .entity(hit.target)
+            .insert(Dead)
+            .try_remove::<(Target, Destination)>();
+        recent_deaths.0.push(hit.target);
+
+        // Nothing left to fight
+        for (entity, target) in target_query.iter() {
+            if target.0 == hit.target {
+                commands
+                    .entity(entity)
+                    .try_remove::<(Target, Destination)>();
+            }
+        }
+    }
+}
+
+fn draw_health_bars(
+    mut gizmos: Gizmos,
+    query: Query<(&Transform, &Size, &Hitpoints), Without<Dead>>,
+) {
+    for (transform, size, hitpoints) in query.iter() {
+        // Just above the entity, as wide as it is
+        let left = transform.translation.truncate() + Vec2::new(-0.5, size.0 as f32 - 0.3);
+        let width = size.0 as f32;
+        let left_over = width * hitpoints.current as f32 / hitpoints.max.max(1) as f32;
+
+        gizmos.line_2d(left, left + Vec2::X * left_over, HEALTH_BAR_COLOR);
+        gizmos.line_2d(
+            left + Vec2::X * left_over,
+            left + Vec2::X * width,
+            HEALTH_BAR_LOST_COLOR,
+        );
+    }
+}
+
+fn hide_dead_npcs(mut query: Query<(&mut Visibility, Has<Dead>), With<Npc>>) {
+    for (mut visibility, dead) in query.iter_mut() {
+        let wanted = if dead {
+            Visibility::Hidden
+        } else {
+            Visibility::Visible
+        };
+        visibility.set_if_neq(wanted);
+    }
+}
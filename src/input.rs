@@ -1,17 +1,37 @@
 use bevy::{prelude::*, window::PrimaryWindow};
 use bevy_egui::{
-    EguiContextPass, EguiContexts, EguiPlugin, egui, input::egui_wants_any_pointer_input,
+    EguiContextPass, EguiContexts, EguiPlugin, egui,
+    input::{egui_wants_any_keyboard_input, egui_wants_any_pointer_input},
 };
 
-use crate::npc::{Npc, Size};
-use crate::player::{Player, PlayerAction, PlayerActionEvent, PlayerModifiers};
+use crate::attack::{AttackEvent, AttackStatus, AttackStyle, NPC_ATTACK_COLOR, Target};
+use crate::combat::expected_damage;
+use crate::history::HistoryEvent;
+use crate::hitpoints::{Dead, Hitpoints};
+use crate::method_file::{CurrentMethodFile, MethodFileEvent};
+use crate::movement::{MovementOrder, NpcMovementOrders};
+use crate::npc::{Npc, NpcId, Size};
+use crate::playback::{
+    MAX_PLAYBACK_SPEED, MIN_PLAYBACK_SPEED, Playback, PlaybackRestartEvent, PlaybackStepEvent,
+};
+use crate::player::{Player, PlayerAction, PlayerActionEvent, PlayerId, player_color};
+use crate::projectile::{Hit, RecentHits};
+use crate::scenario::{Scenario, ScenarioEntity, ScenarioTool, editing_scenario};
 use crate::schedule::{EditingSet, FreeRoamSet};
-use crate::sequence::ActionSequence;
+use crate::sequence::{ActionSequence, SequenceEditEvent};
+use crate::snapshot::SequenceSnapshots;
 use crate::state::ToolState;
 
 /// Max length of the tick sequence shown in the UI
 const MAX_UI_TICK_SEQUENCE_LEN: usize = 10;
 
+/// Size of hitsplats drawn over entities that got hit, in screen pixels
+const HITSPLAT_RADIUS: f32 = 10.;
+
+/// Colors of hitsplats for hits that did and didn't do any damage
+const HITSPLAT_COLOR: egui::Color32 = egui::Color32::from_rgb(170, 20, 20);
+const HITSPLAT_MISS_COLOR: egui::Color32 = egui::Color32::from_rgb(30, 60, 170);
+
 #[derive(Event, Default, Debug)]
 pub struct EditingResetEvent;
 
@@ -24,6 +44,15 @@ impl Plugin for UserInputPlugin {
         })
         .add_event::<EditingResetEvent>()
         .add_systems(EguiContextPass, draw_ui)
+        .add_systems(
+            EguiContextPass,
+            draw_sequence_ui.run_if(in_state(ToolState::Editing)),
+        )
+        .add_systems(
+            EguiContextPass,
+            draw_playback_ui.run_if(in_state(ToolState::Playback)),
+        )
+        .add_systems(EguiContextPass, draw_hitsplats)
         .add_systems(
             Update,
             mouse_input
@@ -34,21 +63,69 @@ impl Plugin for UserInputPlugin {
             Update,
             mouse_input
                 .run_if(not(egui_wants_any_pointer_input))
+                .run_if(not(editing_scenario))
+                .in_set(EditingSet::UserInput),
+        )
+        .add_systems(
+            Update,
+            drag_start_positions
+                .run_if(not(egui_wants_any_pointer_input))
+                .run_if(resource_equals(ScenarioTool::PlaceStart))
+                .in_set(EditingSet::UserInput),
+        )
+        .add_systems(
+            Update,
+            paint_blocked_tiles
+                .run_if(not(egui_wants_any_pointer_input))
+                .run_if(resource_equals(ScenarioTool::PaintBlocked))
+                .in_set(EditingSet::UserInput),
+        )
+        .add_systems(
+            Update,
+            history_keyboard_input
+                .run_if(not(egui_wants_any_keyboard_input))
                 .in_set(EditingSet::UserInput),
         );
     }
 }
 
-#[allow(clippy::too_many_arguments)]
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 fn draw_ui(
     mut contexts: EguiContexts,
     state: Res<State<ToolState>>,
     mut next_state: ResMut<NextState<ToolState>>,
     mut editing_reset_evw: EventWriter<EditingResetEvent>,
+    mut method_file_evw: EventWriter<MethodFileEvent>,
+    mut sequence_edit_evw: EventWriter<SequenceEditEvent>,
+    current_method_file: Res<CurrentMethodFile>,
+    scenario_tool: Res<ScenarioTool>,
     mut action_sequence: ResMut<ActionSequence>,
-    mut player_modifiers: ResMut<PlayerModifiers>,
+    snapshots: Res<SequenceSnapshots>,
     window_query: Query<&Window, With<PrimaryWindow>>,
-    player_query: Query<&Transform, With<Player>>,
+    scenario: Res<Scenario>,
+    player_query: Query<
+        (
+            Entity,
+            &PlayerId,
+            &Transform,
+            Option<&AttackStatus>,
+            Has<Target>,
+            Option<&NpcMovementOrders>,
+            &Hitpoints,
+        ),
+        With<Player>,
+    >,
+    npc_query: Query<
+        (
+            &NpcId,
+            &AttackStyle,
+            Option<&AttackStatus>,
+            Has<Target>,
+            &Hitpoints,
+            Has<Dead>,
+        ),
+        With<Npc>,
+    >,
     mut reset_window: Local<bool>,
 ) {
     let window = window_query.single().expect("SHOULD BE ONE WINDOW");
@@ -69,6 +146,7 @@ fn draw_ui(
             ui.horizontal(|ui| {
                 ui.selectable_value(&mut desired_state, ToolState::Editing, "Method Creation");
                 ui.selectable_value(&mut desired_state, ToolState::FreeRoam, "Free Roam");
+                ui.selectable_value(&mut desired_state, ToolState::Playback, "Playback");
             });
             if &desired_state != state.get() {
                 next_state.set(desired_state);
@@ -79,49 +157,160 @@ fn draw_ui(
 
             ui.separator();
 
-            if ui.button("Reset").clicked() || *reset_window {
-                *reset_window = true;
+            ui.horizontal(|ui| {
+                if ui.button("Open").clicked() {
+                    method_file_evw.write(MethodFileEvent::Open);
+                }
+                if ui.button("Save").clicked() {
+                    method_file_evw.write(MethodFileEvent::Save);
+                }
+                if ui.button("Save As").clicked() {
+                    method_file_evw.write(MethodFileEvent::SaveAs);
+                }
+                if ui.button("Reset").clicked() || *reset_window {
+                    *reset_window = true;
+                }
+            });
+            if let Some(path) = &current_method_file.path {
+                ui.label(format!("File: {}", path.display()));
+            }
+            if let Some(error) = &current_method_file.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+
+            // Player being edited, the timeline and clicks on the grid are for their lane
+            let mut selected_player = action_sequence.selected_player;
+            ui.horizontal(|ui| {
+                ui.label("Player");
+                for spawn in scenario.players.iter() {
+                    let [r, g, b, _] = player_color(spawn.id).to_srgba().to_u8_array();
+                    ui.selectable_value(
+                        &mut selected_player,
+                        spawn.id,
+                        egui::RichText::new(&spawn.name).color(egui::Color32::from_rgb(r, g, b)),
+                    );
+                }
+            });
+            if selected_player != action_sequence.selected_player {
+                action_sequence.selected_player = selected_player;
             }
 
             // Player Information
-            let current_transform = player_query.single().expect("SHOULD BE ONE PLAYER");
-            let current_location = current_transform.translation.truncate();
-            let current_action = action_sequence.sequence[action_sequence.target_tick]
-                .0
-                .clone();
+            if let Some((
+                _,
+                _,
+                current_transform,
+                attack_status,
+                has_target,
+                movement_orders,
+                hitpoints,
+            )) = player_query
+                .iter()
+                .find(|(_, id, ..)| **id == action_sequence.selected_player)
+            {
+                let current_location = current_transform.translation.truncate();
+                let current_actions = action_sequence.selected_lane().ticks
+                    [action_sequence.target_tick]
+                    .0
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
 
-            ui.label(format!("Location: {current_location}"));
-            ui.label(format!("Action: {current_action}"));
+                ui.label(format!("Location: {current_location}"));
+                ui.label(format!(
+                    "Hitpoints: {}/{}",
+                    hitpoints.current, hitpoints.max
+                ));
+                ui.label(format!("Actions: {current_actions}"));
+                if let (Some(status), true) = (attack_status, has_target) {
+                    ui.label(format!("Attack: {status}"));
+                }
 
-            // Sequence Modification
-            let sequence_len = action_sequence.sequence.len();
+                // Npcs whose movement the player's is processed along with instead of after
+                let moves_with = movement_orders
+                    .into_iter()
+                    .flat_map(|orders| orders.0.iter())
+                    .filter(|(_, order)| *order == MovementOrder::First)
+                    .filter_map(|(npc, _)| npc_query.get(*npc).ok())
+                    .filter_map(|(id, ..)| scenario.npcs.iter().find(|spawn| spawn.id == *id))
+                    .map(|spawn| spawn.name.as_str())
+                    .collect::<Vec<_>>();
+                if !moves_with.is_empty() {
+                    ui.label(format!("Moves along with: {}", moves_with.join(", ")));
+                }
+            }
+
+            // Why each npc is or isn't attacking
+            for (id, style, attack_status, has_target, hitpoints, dead) in npc_query.iter() {
+                let Some(spawn) = scenario.npcs.iter().find(|spawn| spawn.id == *id) else {
+                    continue;
+                };
+                if dead {
+                    ui.label(format!("{}: Dead", spawn.name));
+                    continue;
+                }
+                let (Some(status), true) = (attack_status, has_target) else {
+                    continue;
+                };
+                ui.label(format!(
+                    "{} ({style}, {}/{} HP): {status}",
+                    spawn.name, hitpoints.current, hitpoints.max
+                ));
+            }
+
+            // Sequence Modification, which only makes sense at the start while placing
+            if *scenario_tool == ScenarioTool::PlaceStart {
+                ui.disable();
+            }
+            let players: Vec<(Entity, PlayerId)> = player_query
+                .iter()
+                .map(|(entity, id, ..)| (entity, *id))
+                .collect();
+            let entity_name = |entity: Entity| {
+                let player = players
+                    .iter()
+                    .find(|(player, _)| *player == entity)
+                    .and_then(|(_, id)| scenario.player(*id))
+                    .map(|spawn| spawn.name.clone());
+                let npc = || {
+                    let (id, ..) = npc_query.get(entity).ok()?;
+                    scenario
+                        .npcs
+                        .iter()
+                        .find(|spawn| spawn.id == *id)
+                        .map(|spawn| spawn.name.clone())
+                };
+                player.or_else(npc).unwrap_or_else(|| "Unknown".to_string())
+            };
+            let sequence_len = action_sequence.len();
+            // Move a copy so the sequence is only marked as changed when the tick does
+            let mut target_tick = action_sequence.target_tick;
             ui.horizontal(|ui| {
                 let mut starting_num = 0;
                 let mut ending_num = sequence_len;
 
                 if sequence_len > MAX_UI_TICK_SEQUENCE_LEN {
-                    if action_sequence.target_tick + (MAX_UI_TICK_SEQUENCE_LEN / 2) < sequence_len
-                        && action_sequence.target_tick > (MAX_UI_TICK_SEQUENCE_LEN / 2)
-                    {
-                        starting_num = action_sequence.target_tick - (MAX_UI_TICK_SEQUENCE_LEN / 2);
-                        ending_num = action_sequence.target_tick + (MAX_UI_TICK_SEQUENCE_LEN / 2);
-                    } else if action_sequence.target_tick + (MAX_UI_TICK_SEQUENCE_LEN / 2)
-                        < sequence_len
+                    if target_tick + (MAX_UI_TICK_SEQUENCE_LEN / 2) < sequence_len
+                        && target_tick > (MAX_UI_TICK_SEQUENCE_LEN / 2)
                     {
+                        starting_num = target_tick - (MAX_UI_TICK_SEQUENCE_LEN / 2);
+                        ending_num = target_tick + (MAX_UI_TICK_SEQUENCE_LEN / 2);
+                    } else if target_tick + (MAX_UI_TICK_SEQUENCE_LEN / 2) < sequence_len {
                         ending_num = MAX_UI_TICK_SEQUENCE_LEN;
-                    } else if action_sequence.target_tick > (MAX_UI_TICK_SEQUENCE_LEN / 2) {
+                    } else if target_tick > (MAX_UI_TICK_SEQUENCE_LEN / 2) {
                         starting_num = sequence_len - MAX_UI_TICK_SEQUENCE_LEN;
                     } else {
                         panic!("SHOULD HAVE COVERED ALL CASES!");
                     }
                 }
 
-                ui.add_enabled_ui(action_sequence.target_tick > 0, |ui| {
+                ui.add_enabled_ui(target_tick > 0, |ui| {
                     if ui.button("<<").clicked() {
-                        action_sequence.target_tick = 0;
+                        target_tick = 0;
                     }
-                    if ui.button("<").clicked() && action_sequence.target_tick > 0 {
-                        action_sequence.target_tick -= 1;
+                    if ui.button("<").clicked() && target_tick > 0 {
+                        target_tick -= 1;
                     }
                 });
 
@@ -130,7 +319,16 @@ fn draw_ui(
                 }
                 ui.add_enabled_ui(state.get() == &ToolState::Editing, |ui| {
                     for i in starting_num..ending_num {
-                        ui.selectable_value(&mut action_sequence.target_tick, i, format!("{i}"));
+                        let (attacks, hits) = (snapshots.attacks(i), snapshots.hits(i));
+                        let response = ui.selectable_value(
+                            &mut target_tick,
+                            i,
+                            prv_tick_label(i, attacks, hits, &players),
+                        );
+                        let details = prv_tick_details(i, &snapshots, entity_name);
+                        if !details.is_empty() {
+                            response.on_hover_text(details);
+                        }
                     }
                 });
                 if ending_num < sequence_len {
@@ -138,41 +336,24 @@ fn draw_ui(
                 }
 
                 #[allow(clippy::collapsible_else_if)]
-                if action_sequence.target_tick == sequence_len - 1 {
+                if target_tick == sequence_len - 1 {
                     if ui.button("+").clicked() {
-                        action_sequence
-                            .sequence
-                            .extend_from_within(sequence_len - 1..);
-                        action_sequence.target_tick += 1;
+                        sequence_edit_evw.write(SequenceEditEvent::InsertAfter(target_tick));
                     }
                 } else {
-                    if ui.button(">").clicked() && action_sequence.target_tick < sequence_len - 1 {
-                        action_sequence.target_tick += 1;
+                    if ui.button(">").clicked() && target_tick < sequence_len - 1 {
+                        target_tick += 1;
                     }
                 }
-                ui.add_enabled_ui(action_sequence.target_tick < sequence_len - 1, |ui| {
+                ui.add_enabled_ui(target_tick < sequence_len - 1, |ui| {
                     if ui.button(">>").clicked() {
-                        action_sequence.target_tick = sequence_len - 1;
+                        target_tick = sequence_len - 1;
                     }
                 });
             });
-        });
-
-    // Player modifier UI section
-    egui::Window::new("Player Modifiers")
-        .collapsible(false)
-        .resizable(false)
-        .movable(false)
-        .current_pos((window_width, 0.))
-        .max_width(200.)
-        .show(contexts.ctx_mut(), |ui| {
-            ui.checkbox(&mut player_modifiers.run, "Run");
-
-            ui.separator();
-
-            ui.label("Weapon");
-            ui.add(egui::Slider::new(&mut player_modifiers.weapon_speed, 2..=7).text("Speed"));
-            ui.add(egui::Slider::new(&mut player_modifiers.weapon_range, 1..=10).text("Range"));
+            if target_tick != action_sequence.target_tick {
+                action_sequence.target_tick = target_tick;
+            }
         });
 
     // Sequence reset confirmation window
@@ -197,36 +378,286 @@ fn draw_ui(
     }
 }
 
+fn draw_sequence_ui(
+    mut contexts: EguiContexts,
+    mut sequence_edit_evw: EventWriter<SequenceEditEvent>,
+    action_sequence: Res<ActionSequence>,
+    scenario_tool: Res<ScenarioTool>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    // Inclusive range of ticks to duplicate
+    mut duplicate_range: Local<(usize, usize)>,
+) {
+    let window = window_query.single().expect("SHOULD BE ONE WINDOW");
+
+    egui::Window::new("Sequence")
+        .collapsible(false)
+        .resizable(false)
+        .movable(false)
+        .current_pos((window.width(), window.height()))
+        .show(contexts.ctx_mut(), |ui| {
+            // Same as the rest of the sequence modification, only makes sense outside of placing
+            if *scenario_tool == ScenarioTool::PlaceStart {
+                ui.disable();
+            }
+
+            let target_tick = action_sequence.target_tick;
+            let last_tick = action_sequence.len() - 1;
+
+            ui.label(format!("Tick {target_tick}"));
+
+            // Every input of the selected player on the tick in the order they're made
+            let actions = &action_sequence.selected_lane().ticks[target_tick].0;
+            for (index, action) in actions.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{}. {action}", index + 1));
+                    if ui.add_enabled(index > 0, egui::Button::new("⬆")).clicked() {
+                        sequence_edit_evw
+                            .write(SequenceEditEvent::MoveInputEarlier(target_tick, index));
+                    }
+                    if ui
+                        .add_enabled(index + 1 < actions.len(), egui::Button::new("⬇"))
+                        .clicked()
+                    {
+                        sequence_edit_evw
+                            .write(SequenceEditEvent::MoveInputLater(target_tick, index));
+                    }
+                    if ui
+                        .add_enabled(actions.len() > 1, egui::Button::new("🗙"))
+                        .clicked()
+                    {
+                        sequence_edit_evw.write(SequenceEditEvent::RemoveInput(target_tick, index));
+                    }
+                });
+            }
+            ui.label("Shift+click to add another input to the tick");
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui.button("Insert Before").clicked() {
+                    sequence_edit_evw.write(SequenceEditEvent::InsertBefore(target_tick));
+                }
+                if ui.button("Insert After").clicked() {
+                    sequence_edit_evw.write(SequenceEditEvent::InsertAfter(target_tick));
+                }
+                if ui
+                    .add_enabled(last_tick > 0, egui::Button::new("Delete"))
+                    .clicked()
+                {
+                    sequence_edit_evw.write(SequenceEditEvent::Delete(target_tick));
+                }
+            });
+
+            ui.separator();
+
+            let (start, end) = &mut *duplicate_range;
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(start)
+                        .range(0..=last_tick)
+                        .prefix("from: "),
+                );
+                ui.add(
+                    egui::DragValue::new(end)
+                        .range(*start..=last_tick)
+                        .prefix("to: "),
+                );
+                if ui.button("Duplicate").clicked() {
+                    sequence_edit_evw.write(SequenceEditEvent::DuplicateRange(*start, *end));
+                }
+            });
+            // Keep the range valid when the sequence gets shorter
+            *start = usize::min(*start, last_tick);
+            *end = (*end).clamp(*start, last_tick);
+        });
+}
+
+fn draw_playback_ui(
+    mut contexts: EguiContexts,
+    mut playback_step_evw: EventWriter<PlaybackStepEvent>,
+    mut playback_restart_evw: EventWriter<PlaybackRestartEvent>,
+    mut playback: ResMut<Playback>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    let window = window_query.single().expect("SHOULD BE ONE WINDOW");
+
+    egui::Window::new("Playback")
+        .collapsible(false)
+        .resizable(false)
+        .movable(false)
+        .current_pos((0., window.height()))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                let play_label = if playback.playing { "Pause" } else { "Play" };
+                ui.add_enabled_ui(!playback.finished(), |ui| {
+                    if ui.button(play_label).clicked() {
+                        playback.playing = !playback.playing;
+                    }
+                    if ui
+                        .add_enabled(!playback.playing, egui::Button::new("Step"))
+                        .clicked()
+                    {
+                        playback_step_evw.write(PlaybackStepEvent);
+                    }
+                });
+                if ui.button("Restart").clicked() {
+                    playback_restart_evw.write(PlaybackRestartEvent);
+                }
+            });
+            ui.add(
+                egui::Slider::new(&mut playback.speed, MIN_PLAYBACK_SPEED..=MAX_PLAYBACK_SPEED)
+                    .logarithmic(true)
+                    .text("Speed"),
+            );
+            ui.label(format!("Tick: {} / {}", playback.tick, playback.last_tick));
+        });
+}
+
+fn draw_hitsplats(
+    mut contexts: EguiContexts,
+    recent_hits: Res<RecentHits>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    target_query: Query<(&Transform, &Size)>,
+) {
+    let Ok((camera, camera_transform)) = camera_query.single() else {
+        return;
+    };
+    // Underneath all of the windows
+    let painter = contexts
+        .ctx_mut()
+        .layer_painter(egui::LayerId::background());
+
+    let mut drawn = Vec::new();
+    for hit in recent_hits.0.iter() {
+        let Ok((transform, size)) = target_query.get(hit.target) else {
+            continue;
+        };
+        let center = transform.translation.truncate() + (size.0 as f32 / 2. - 0.5);
+        let Ok(position) = camera.world_to_viewport(camera_transform, center.extend(0.)) else {
+            continue;
+        };
+
+        // Several hits on the same entity at once get stacked on top of each other
+        let stacked = drawn.iter().filter(|entity| **entity == hit.target).count();
+        drawn.push(hit.target);
+        let position = egui::pos2(
+            position.x,
+            position.y - stacked as f32 * HITSPLAT_RADIUS * 2.,
+        );
+
+        let color = match hit.damage {
+            0 => HITSPLAT_MISS_COLOR,
+            _ => HITSPLAT_COLOR,
+        };
+        painter.circle_filled(position, HITSPLAT_RADIUS, color);
+        painter.text(
+            position,
+            egui::Align2::CENTER_CENTER,
+            hit.damage.to_string(),
+            egui::FontId::proportional(HITSPLAT_RADIUS * 1.2),
+            egui::Color32::WHITE,
+        );
+    }
+}
+
+// Helper to build the label of a tick in the timeline, with a marker for each player that
+// attacked in their own color and one for the npcs, followed by the same for attacks that hit
+fn prv_tick_label(
+    tick: usize,
+    attacks: &[AttackEvent],
+    hits: &[Hit],
+    players: &[(Entity, PlayerId)],
+) -> egui::text::LayoutJob {
+    let mut job = egui::text::LayoutJob::default();
+    job.append(&format!("{tick}"), 0., egui::TextFormat::default());
+
+    let attackers: Vec<Entity> = attacks.iter().map(|attack| attack.attacker).collect();
+    let hitters: Vec<Entity> = hits.iter().map(|hit| hit.attacker).collect();
+    for (marker, attackers) in [("•", attackers), ("×", hitters)] {
+        let npc_attacked = attackers
+            .iter()
+            .any(|attacker| !players.iter().any(|(player, _)| player == attacker));
+        let colors = players
+            .iter()
+            .filter(|(player, _)| attackers.contains(player))
+            .map(|(_, id)| player_color(*id))
+            .chain(npc_attacked.then_some(NPC_ATTACK_COLOR));
+        for color in colors {
+            let [r, g, b, _] = color.to_srgba().to_u8_array();
+            job.append(
+                marker,
+                0.,
+                egui::TextFormat {
+                    color: egui::Color32::from_rgb(r, g, b),
+                    ..default()
+                },
+            );
+        }
+    }
+
+    job
+}
+
+// Helper to describe the attacks made on a tick along with when they hit, the hits landing on it
+// along with when they were made, and anything that died
+fn prv_tick_details(
+    tick: usize,
+    snapshots: &SequenceSnapshots,
+    entity_name: impl Fn(Entity) -> String,
+) -> String {
+    let attacks = snapshots.attacks(tick).iter().map(|attack| {
+        format!(
+            "{} attacks {}, hits on tick {} (+{}), expected {:.1} damage ({:.0}% accuracy, max hit {})",
+            entity_name(attack.attacker),
+            entity_name(attack.target),
+            tick + attack.hit_delay as usize,
+            attack.hit_delay,
+            expected_damage(attack.accuracy, attack.max_hit),
+            attack.accuracy * 100.,
+            attack.max_hit
+        )
+    });
+    let hits = snapshots.hits(tick).iter().map(|hit| {
+        format!(
+            "{} hits {} for {}, attacked on tick {} (+{})",
+            entity_name(hit.attacker),
+            entity_name(hit.target),
+            hit.damage,
+            tick.saturating_sub(hit.delay as usize),
+            hit.delay
+        )
+    });
+    let deaths = snapshots
+        .deaths(tick)
+        .iter()
+        .map(|npc| format!("{} dies", entity_name(*npc)));
+
+    attacks
+        .chain(hits)
+        .chain(deaths)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 fn mouse_input(
     window_query: Query<&Window, With<PrimaryWindow>>,
     camera_query: Query<(&Camera, &GlobalTransform)>,
     input: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
     npc_query: Query<(Entity, &Transform, &Size), With<Npc>>,
+    action_sequence: Res<ActionSequence>,
     mut player_action_evw: EventWriter<PlayerActionEvent>,
 ) {
     if !input.just_pressed(MouseButton::Left) {
         return;
     }
 
-    let Ok(window) = window_query.single() else {
-        return;
-    };
-
-    let Some(window_position) = window.cursor_position() else {
-        return;
-    };
-
-    let Ok((camera, camera_transform)) = camera_query.single() else {
-        return;
-    };
-
-    let Ok(world_position) = camera.viewport_to_world_2d(camera_transform, window_position) else {
+    let Some(clicked_tile) = prv_cursor_tile(&window_query, &camera_query) else {
         return;
     };
 
     let mut target = Entity::PLACEHOLDER;
     for (entity, transform, size) in npc_query.iter() {
-        let clicked_tile = world_position.round();
         if transform.translation.x <= clicked_tile.x
             && clicked_tile.x <= transform.translation.x + (size.0 as f32 - 1.)
             && transform.translation.y <= clicked_tile.y
@@ -237,13 +668,113 @@ fn mouse_input(
         }
     }
 
+    // Holding shift makes another click on the same tick instead of replacing the ones before
+    let queued = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
     if target != Entity::PLACEHOLDER {
         player_action_evw.write(PlayerActionEvent {
+            player: action_sequence.selected_player,
             action: PlayerAction::Attack(target),
+            queued,
+            replayed: false,
         });
     } else {
         player_action_evw.write(PlayerActionEvent {
-            action: PlayerAction::Move(world_position.round()),
+            player: action_sequence.selected_player,
+            action: PlayerAction::Move(clicked_tile),
+            queued,
+            replayed: false,
         });
     }
 }
+
+fn drag_start_positions(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    input: Res<ButtonInput<MouseButton>>,
+    mut scenario: ResMut<Scenario>,
+    // What is being dragged, along with where it was grabbed relative to its SW tile
+    mut dragging: Local<Option<(ScenarioEntity, Vec2)>>,
+) {
+    if !input.pressed(MouseButton::Left) {
+        *dragging = None;
+        return;
+    }
+
+    let Some(tile) = prv_cursor_tile(&window_query, &camera_query) else {
+        return;
+    };
+
+    if input.just_pressed(MouseButton::Left) {
+        *dragging = scenario.entity_at(tile).and_then(|entity| {
+            scenario
+                .position(entity)
+                .map(|position| (entity, tile - position))
+        });
+    }
+
+    let Some((entity, grab_offset)) = *dragging else {
+        return;
+    };
+
+    // Only touch the scenario when something actually moved so it isn't needlessly re-run
+    let position = tile - grab_offset;
+    if scenario.position(entity) != Some(position) {
+        scenario.set_position(entity, position);
+    }
+}
+
+fn paint_blocked_tiles(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    input: Res<ButtonInput<MouseButton>>,
+    mut scenario: ResMut<Scenario>,
+    // Whether tiles are being blocked or unblocked during this drag
+    mut painting: Local<Option<bool>>,
+) {
+    if !input.pressed(MouseButton::Left) {
+        *painting = None;
+        return;
+    }
+
+    let Some(tile) = prv_cursor_tile(&window_query, &camera_query) else {
+        return;
+    };
+
+    // The first tile clicked decides whether the whole drag blocks or unblocks
+    let blocked = *painting.get_or_insert(!scenario.collision.is_blocked(tile));
+    if scenario.collision.is_blocked(tile) != blocked {
+        scenario.collision.set_blocked(tile, blocked);
+    }
+}
+
+fn history_keyboard_input(
+    input: Res<ButtonInput<KeyCode>>,
+    mut history_evw: EventWriter<HistoryEvent>,
+) {
+    if !input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        || !input.just_pressed(KeyCode::KeyZ)
+    {
+        return;
+    }
+
+    if input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        history_evw.write(HistoryEvent::Redo);
+    } else {
+        history_evw.write(HistoryEvent::Undo);
+    }
+}
+
+// Helper to get the tile the cursor is currently over
+fn prv_cursor_tile(
+    window_query: &Query<&Window, With<PrimaryWindow>>,
+    camera_query: &Query<(&Camera, &GlobalTransform)>,
+) -> Option<Vec2> {
+    let window = window_query.single().ok()?;
+    let window_position = window.cursor_position()?;
+    let (camera, camera_transform) = camera_query.single().ok()?;
+    let world_position = camera
+        .viewport_to_world_2d(camera_transform, window_position)
+        .ok()?;
+
+    Some(world_position.round())
+}
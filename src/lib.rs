@@ -0,0 +1,26 @@
+pub mod attack;
+pub mod camera;
+pub mod combat;
+pub mod debug;
+pub mod game_ticks;
+pub mod gear;
+pub mod history;
+pub mod hitpoints;
+pub mod input;
+pub mod method_file;
+pub mod movement;
+pub mod npc;
+pub mod npc_library;
+pub mod pathfinding;
+pub mod playback;
+pub mod player;
+pub mod projectile;
+pub mod rng;
+pub mod scenario;
+pub mod schedule;
+pub mod sequence;
+pub mod simulation;
+pub mod snapshot;
+pub mod state;
+pub mod ui;
+pub mod world;
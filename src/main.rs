@@ -1,22 +1,14 @@
-mod attack;
-mod camera;
-mod debug;
-mod game_ticks;
-mod input;
-mod movement;
-mod npc;
-mod player;
-mod schedule;
-mod sequence;
-mod state;
-mod world;
-
 #[cfg(feature = "debug")]
 use bevy::log::LogPlugin;
 use bevy::prelude::*;
 #[cfg(feature = "debug")]
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
+use osrs_method_creator::{
+    attack, camera, debug, game_ticks, gear, history, hitpoints, input, method_file, movement, npc,
+    npc_library, playback, player, projectile, scenario, schedule, sequence, state, ui, world,
+};
+
 /// Scale of gizmo line width, proportional to the projection scale
 const GIZMO_LINE_WIDTH_SCALE: f32 = 0.09;
 
@@ -38,13 +30,24 @@ fn main() {
         .add_plugins(camera::CameraPlugin)
         .add_plugins(debug::DebugPlugin)
         .add_plugins(game_ticks::GameTickPlugin)
+        .add_plugins(gear::GearPlugin)
+        .add_plugins(history::HistoryPlugin)
+        .add_plugins(hitpoints::HitpointsPlugin)
         .add_plugins(input::UserInputPlugin)
+        .add_plugins(method_file::MethodFilePlugin)
         .add_plugins(movement::MovementPlugin)
         .add_plugins(npc::NpcPlugin)
+        .add_plugins(npc_library::NpcLibraryPlugin)
+        .add_plugins(playback::PlaybackPlugin)
         .add_plugins(player::PlayerPlugin)
+        .add_plugins(projectile::ProjectilePlugin)
+        .add_plugins(scenario::ScenarioPlugin)
         .add_plugins(schedule::SchedulePlugin)
         .add_plugins(sequence::SequencePlugin)
         .add_plugins(state::StatePlugin)
+        .add_plugins(ui::gear_editor::GearEditorPlugin)
+        .add_plugins(ui::npc_editor::NpcEditorPlugin)
+        .add_plugins(ui::scenario_editor::ScenarioEditorPlugin)
         .add_plugins(world::WorldPlugin);
 
     #[cfg(feature = "debug")]
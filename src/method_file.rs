@@ -0,0 +1,441 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::history::EditHistory;
+use crate::npc::{Npc, NpcEntities, NpcId};
+use crate::player::{PlayerAction, PlayerId, PlayerModifiers};
+use crate::scenario::Scenario;
+use crate::schedule::EditingSet;
+use crate::sequence::{ActionSequence, SequenceLane};
+use crate::snapshot::SequenceSnapshots;
+use crate::state::EditingState;
+
+/// Version of the method file format, bump whenever the format changes
+pub const METHOD_FILE_VERSION: u32 = 1;
+
+/// Extension of method files
+const METHOD_FILE_EXTENSION: &str = "ron";
+
+/// Version a method was saved with, read before anything else so files from newer versions fail
+/// with a clear error instead of whatever part of them can't be parsed
+#[derive(Debug, Deserialize)]
+struct SavedVersion {
+    version: u32,
+}
+
+/// A method as it is stored on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MethodFile {
+    pub version: u32,
+    pub scenario: Scenario,
+    /// Inputs of every player
+    pub lanes: Vec<SavedLane>,
+}
+
+/// A `SequenceLane` as it is stored on disk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedLane {
+    pub player: PlayerId,
+    /// Inputs of every tick, in the order they're made
+    pub ticks: Vec<(Vec<SavedAction>, PlayerModifiers)>,
+}
+
+/// A `PlayerAction` as it is stored on disk, with npcs referred to by their `NpcId` instead of
+/// their `Entity`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SavedAction {
+    Idle,
+    Move(Vec2),
+    Attack(NpcId),
+    Equip(String),
+}
+
+/// The file the current method was last opened from or saved to
+#[derive(Resource, Debug, Default)]
+pub struct CurrentMethodFile {
+    pub path: Option<PathBuf>,
+    /// Why the last open or save failed, if it did
+    pub error: Option<String>,
+}
+
+/// Event to request a file operation on the current method
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub enum MethodFileEvent {
+    Open,
+    Save,
+    SaveAs,
+}
+
+impl MethodFile {
+    /// Read a method from disk, making sure it can actually be used
+    pub fn read(path: &Path) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Read a method from its saved contents, making sure it can actually be used
+    pub fn parse(contents: &str) -> Result<Self> {
+        let SavedVersion { version } = ron::from_str(contents)?;
+        if version > METHOD_FILE_VERSION {
+            return Err(format!(
+                "file version {version} is newer than the supported version {METHOD_FILE_VERSION}"
+            )
+            .into());
+        }
+
+        let method: MethodFile = ron::from_str(contents)?;
+
+        let players = &method.scenario.players;
+        if players.is_empty() {
+            return Err("scenario has no players".into());
+        }
+        for spawn in players.iter() {
+            if method
+                .lanes
+                .iter()
+                .filter(|lane| lane.player == spawn.id)
+                .count()
+                != 1
+            {
+                return Err(format!("player {} doesn't have exactly one lane", spawn.id.0).into());
+            }
+        }
+        if let Some(lane) = method
+            .lanes
+            .iter()
+            .find(|lane| !players.iter().any(|spawn| spawn.id == lane.player))
+        {
+            return Err(format!("lane of unknown player {}", lane.player.0).into());
+        }
+
+        let sequence_len = method.lanes[0].ticks.len();
+        if sequence_len == 0 {
+            return Err("sequence is empty".into());
+        }
+        if method
+            .lanes
+            .iter()
+            .any(|lane| lane.ticks.len() != sequence_len)
+        {
+            return Err("lanes don't all have the same number of ticks".into());
+        }
+
+        if let Some(spawn) = method
+            .scenario
+            .npcs
+            .iter()
+            .find(|spawn| spawn.attacks.is_empty())
+        {
+            return Err(format!("npc {} has no attacks", spawn.id.0).into());
+        }
+        for spawn in method.scenario.npcs.iter() {
+            if let Some(target) = spawn.target
+                && !players.iter().any(|player| player.id == target)
+            {
+                return Err(
+                    format!("npc {} targets unknown player {}", spawn.id.0, target.0).into(),
+                );
+            }
+        }
+
+        let gear = &method.scenario.gear;
+        for spawn in players.iter() {
+            if let Some(name) = &spawn.starting_gear
+                && gear.get(name).is_none()
+            {
+                return Err(format!("starting gear {name} doesn't exist").into());
+            }
+        }
+
+        for lane in method.lanes.iter() {
+            for (tick, (actions, _)) in lane.ticks.iter().enumerate() {
+                if actions.is_empty() {
+                    return Err(
+                        format!("tick {tick} of player {} has no inputs", lane.player.0).into(),
+                    );
+                }
+
+                for action in actions.iter() {
+                    match action {
+                        SavedAction::Attack(id)
+                            if !method.scenario.npcs.iter().any(|spawn| spawn.id == *id) =>
+                        {
+                            return Err(format!("sequence attacks unknown npc {}", id.0).into());
+                        }
+                        SavedAction::Equip(name) if gear.get(name).is_none() => {
+                            return Err(format!("sequence equips unknown gear {name}").into());
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+
+        Ok(method)
+    }
+
+    /// Write the method to disk
+    pub fn write(&self, path: &Path) -> Result {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, contents)?;
+
+        Ok(())
+    }
+
+    /// Convert the stored lanes to ones with actual entities, using `npc_entity` to find the
+    /// entity of each npc
+    pub fn player_lanes(
+        &self,
+        npc_entity: impl Fn(NpcId) -> Option<Entity>,
+    ) -> Result<Vec<SequenceLane>> {
+        self.lanes
+            .iter()
+            .map(|lane| {
+                let ticks = lane
+                    .ticks
+                    .iter()
+                    .map(|(actions, modifiers)| {
+                        let actions = actions
+                            .iter()
+                            .map(|action| {
+                                Ok(match action {
+                                    SavedAction::Idle => PlayerAction::Idle,
+                                    SavedAction::Move(dest) => PlayerAction::Move(*dest),
+                                    SavedAction::Attack(id) => PlayerAction::Attack(
+                                        npc_entity(*id)
+                                            .ok_or(format!("no entity for npc {}", id.0))?,
+                                    ),
+                                    SavedAction::Equip(name) => PlayerAction::Equip(name.clone()),
+                                })
+                            })
+                            .collect::<Result<_>>()?;
+
+                        Ok((actions, modifiers.clone()))
+                    })
+                    .collect::<Result<_>>()?;
+
+                Ok(SequenceLane {
+                    player: lane.player,
+                    ticks,
+                })
+            })
+            .collect()
+    }
+}
+
+pub struct MethodFilePlugin;
+
+impl Plugin for MethodFilePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CurrentMethodFile::default())
+            .add_event::<MethodFileEvent>()
+            .add_systems(
+                Update,
+                (save_method, open_method).in_set(EditingSet::SequenceUpdates),
+            );
+    }
+}
+
+fn save_method(
+    mut method_file_evr: EventReader<MethodFileEvent>,
+    mut current_file: ResMut<CurrentMethodFile>,
+    action_sequence: Res<ActionSequence>,
+    scenario: Res<Scenario>,
+    npc_query: Query<&NpcId, With<Npc>>,
+) {
+    for event in method_file_evr.read() {
+        let path = match (event, &current_file.path) {
+            (MethodFileEvent::Open, _) => continue,
+            (MethodFileEvent::Save, Some(path)) => path.clone(),
+            (MethodFileEvent::Save | MethodFileEvent::SaveAs, _) => {
+                let Some(path) = prv_file_dialog().save_file() else {
+                    continue;
+                };
+                path
+            }
+        };
+
+        let result = prv_saved_lanes(&action_sequence, npc_query).and_then(|lanes| {
+            MethodFile {
+                version: METHOD_FILE_VERSION,
+                scenario: scenario.clone(),
+                lanes,
+            }
+            .write(&path)
+        });
+
+        match result {
+            Ok(()) => {
+                current_file.path = Some(path);
+                current_file.error = None;
+            }
+            Err(error) => {
+                current_file.error = Some(format!("Failed to save {}: {error}", path.display()));
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn open_method(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut method_file_evr: EventReader<MethodFileEvent>,
+    mut current_file: ResMut<CurrentMethodFile>,
+    mut action_sequence: ResMut<ActionSequence>,
+    mut scenario: ResMut<Scenario>,
+    mut snapshots: ResMut<SequenceSnapshots>,
+    mut history: ResMut<EditHistory>,
+    mut next_state: ResMut<NextState<EditingState>>,
+    mut npc_entities: ResMut<NpcEntities>,
+) {
+    for event in method_file_evr.read() {
+        if *event != MethodFileEvent::Open {
+            continue;
+        }
+
+        let Some(path) = prv_file_dialog().pick_file() else {
+            continue;
+        };
+
+        let method = match MethodFile::read(&path) {
+            Ok(method) => method,
+            Err(error) => {
+                current_file.error = Some(format!("Failed to open {}: {error}", path.display()));
+                continue;
+            }
+        };
+
+        // Replace the current npcs with the ones from the method
+        npc_entities.despawn_all(&mut commands);
+        for spawn in method.scenario.npcs.iter() {
+            npc_entities.spawn(&mut commands, &mut meshes, &mut materials, spawn);
+        }
+
+        let lanes = method
+            .player_lanes(|id| npc_entities.get(id))
+            .expect("SHOULD HAVE AN ENTITY FOR EVERY NPC");
+
+        *action_sequence = ActionSequence {
+            target_tick: 0,
+            current_tick: 0,
+            selected_player: lanes[0].player,
+            lanes,
+        };
+        *scenario = method.scenario;
+        // None of the old npcs are around anymore
+        snapshots.clear();
+        // Edits to a different method can't be undone into this one
+        history.clear();
+        current_file.path = Some(path);
+        current_file.error = None;
+
+        // Re-run the sequence from the start with the new setup
+        next_state.set(EditingState::Reconciliation);
+    }
+}
+
+fn prv_saved_lanes(
+    action_sequence: &ActionSequence,
+    npc_query: Query<&NpcId, With<Npc>>,
+) -> Result<Vec<SavedLane>> {
+    action_sequence
+        .lanes
+        .iter()
+        .map(|lane| {
+            let ticks = lane
+                .ticks
+                .iter()
+                .map(|(actions, modifiers)| {
+                    let actions = actions
+                        .iter()
+                        .map(|action| {
+                            Ok(match action {
+                                PlayerAction::Idle => SavedAction::Idle,
+                                PlayerAction::Move(dest) => SavedAction::Move(*dest),
+                                PlayerAction::Attack(target) => {
+                                    SavedAction::Attack(*npc_query.get(*target)?)
+                                }
+                                PlayerAction::Equip(name) => SavedAction::Equip(name.clone()),
+                            })
+                        })
+                        .collect::<Result<_>>()?;
+
+                    Ok((actions, modifiers.clone()))
+                })
+                .collect::<Result<_>>()?;
+
+            Ok(SavedLane {
+                player: lane.player,
+                ticks,
+            })
+        })
+        .collect()
+}
+
+fn prv_file_dialog() -> rfd::FileDialog {
+    rfd::FileDialog::new().add_filter("Method", &[METHOD_FILE_EXTENSION])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attack::AttackStyle;
+    use crate::combat::GearBonuses;
+    use crate::gear::GearSetup;
+
+    #[test]
+    fn saved_methods_read_back_the_same() {
+        let mut scenario = Scenario {
+            seed: 1234,
+            ..default()
+        };
+        scenario.collision.set_blocked(Vec2::new(3., -2.), true);
+        scenario.gear.setups.push(GearSetup {
+            name: String::from("Bow"),
+            weapon: None,
+            style: AttackStyle::Ranged,
+            speed: 4,
+            range: 7,
+            projectile: None,
+            bonuses: GearBonuses {
+                attack: 50,
+                strength: 10,
+                spell_max_hit: 0,
+            },
+        });
+        scenario.players[0].starting_gear = Some(String::from("Bow"));
+        let method = MethodFile {
+            version: METHOD_FILE_VERSION,
+            lanes: vec![SavedLane {
+                player: scenario.players[0].id,
+                ticks: vec![
+                    (
+                        vec![SavedAction::Move(Vec2::new(2.5, -1.))],
+                        PlayerModifiers { run: true },
+                    ),
+                    (
+                        vec![
+                            SavedAction::Equip(String::from("Bow")),
+                            SavedAction::Attack(scenario.npcs[0].id),
+                        ],
+                        PlayerModifiers::default(),
+                    ),
+                    (vec![SavedAction::Idle], PlayerModifiers::default()),
+                ],
+            }],
+            scenario,
+        };
+
+        let contents = ron::ser::to_string_pretty(&method, ron::ser::PrettyConfig::default())
+            .expect("METHOD SHOULD SERIALIZE");
+        let read = MethodFile::parse(&contents).expect("SAVED METHOD SHOULD PARSE");
+
+        assert_eq!(read.version, method.version);
+        assert_eq!(read.scenario, method.scenario);
+        assert_eq!(read.lanes, method.lanes);
+    }
+}
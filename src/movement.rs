@@ -1,8 +1,17 @@
+use std::collections::VecDeque;
+
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    game_ticks::GameTickEvent,
-    schedule::{EditingCatchup, EditingCatchupSet, FreeRoamSet},
+    attack::Target,
+    game_ticks::{GameTickEvent, ProcessingOrder},
+    npc::Size,
+    pathfinding::find_path,
+    schedule::{
+        EditingCatchup, EditingCatchupSet, FreeRoamSet, Headless, HeadlessSet, PlaybackSet,
+    },
+    world::CollisionMap,
 };
 
 /// Component to indicate a desire to move to the given location
@@ -15,19 +24,63 @@ pub struct Destination(pub Vec2);
 pub struct Speed(pub u8);
 
 /// How the entity moves
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MovementType {
     CardinalFirst,
     DiagonalFirst,
+    /// Takes single steps straight towards the destination without ever routing around
+    /// anything, like npcs do
+    Dumb,
+    /// Walks along the path found by the game's pathfinder, like players do
+    Pathfinding,
+}
+
+/// Path being walked by an entity that uses the pathfinder
+#[derive(Component, Debug, Clone)]
+pub struct Path {
+    /// Destination the path was found for
+    destination: Vec2,
+    /// Where the entity was after it last walked along the path
+    position: Vec2,
+    checkpoints: VecDeque<Vec2>,
 }
 
-/// The ordering of movement
-#[derive(Component, Debug, PartialEq)]
+/// The ordering of movement, npcs always move `First`
+#[derive(Component, Debug, Clone, PartialEq)]
 pub enum MovementOrder {
     First,
     Second,
 }
 
+/// How a player's movement is ordered relative to each npc moving this tick, decided anew every
+/// tick. `First` means it's processed along with the npc's, `Second` or no entry means after it.
+#[derive(Component, Debug, Default, Clone, PartialEq)]
+pub struct NpcMovementOrders(pub Vec<(Entity, MovementOrder)>);
+
+/// Helper that moves towards a destination, returning the delta movement and speed left
+type MovementFn = fn(Vec2, Vec2, u8, u8, &CollisionMap) -> (Vec2, u8);
+
+impl std::fmt::Display for MovementType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MovementType::CardinalFirst => write!(f, "Cardinal first"),
+            MovementType::DiagonalFirst => write!(f, "Diagonal first"),
+            MovementType::Dumb => write!(f, "Dumb"),
+            MovementType::Pathfinding => write!(f, "Pathfinding"),
+        }
+    }
+}
+
+impl NpcMovementOrders {
+    /// Ordering relative to the given npc
+    pub fn get(&self, npc: Entity) -> Option<&MovementOrder> {
+        self.0
+            .iter()
+            .find(|(entity, _)| *entity == npc)
+            .map(|(_, order)| order)
+    }
+}
+
 impl Default for Speed {
     fn default() -> Self {
         Self(1)
@@ -61,10 +114,92 @@ impl Plugin for MovementPlugin {
             move_second
                 .run_if(on_event::<GameTickEvent>)
                 .in_set(EditingCatchupSet::SecondMovement),
+        )
+        .add_systems(
+            Update,
+            move_first
+                .run_if(on_event::<GameTickEvent>)
+                .in_set(PlaybackSet::FirstMovement),
+        )
+        .add_systems(
+            Update,
+            move_second
+                .run_if(on_event::<GameTickEvent>)
+                .in_set(PlaybackSet::SecondMovement),
+        )
+        .add_systems(
+            Headless,
+            move_first
+                .run_if(on_event::<GameTickEvent>)
+                .in_set(HeadlessSet::FirstMovement),
+        )
+        .add_systems(
+            Headless,
+            move_second
+                .run_if(on_event::<GameTickEvent>)
+                .in_set(HeadlessSet::SecondMovement),
         );
     }
 }
 
+/// Order an entity's movement is processed in this tick. Npcs have their own, a player moves in
+/// the order it has relative to the npc it's going after.
+pub fn movement_order(
+    order: Option<&MovementOrder>,
+    npc_orders: Option<&NpcMovementOrders>,
+    target: Option<&Target>,
+) -> MovementOrder {
+    order
+        .or_else(|| npc_orders?.get(target?.0))
+        .cloned()
+        .unwrap_or(MovementOrder::Second)
+}
+
+/// Where an entity would end up after moving towards the destination for a single tick, without
+/// actually moving it. Moving with less speed always ends up somewhere along the same way.
+pub fn moved_position(
+    start: Vec2,
+    destination: Vec2,
+    speed: u8,
+    size: u8,
+    movement_type: &MovementType,
+    collision_map: &CollisionMap,
+) -> Vec2 {
+    let movements: &[MovementFn] = match movement_type {
+        MovementType::CardinalFirst => &[prv_move_cardinally, prv_move_diagonally],
+        MovementType::DiagonalFirst => &[prv_move_diagonally, prv_move_cardinally],
+        MovementType::Dumb => &[prv_move_dumbly],
+        MovementType::Pathfinding => {
+            let mut position = start;
+            let mut checkpoints = find_path(start, destination, size, collision_map).into_iter();
+            let mut checkpoint = checkpoints.next();
+            for _ in 0..speed {
+                let Some(next) = checkpoint else {
+                    break;
+                };
+
+                position += (next - position).clamp(Vec2::NEG_ONE, Vec2::ONE);
+                if position == next {
+                    checkpoint = checkpoints.next();
+                }
+            }
+            return position;
+        }
+    };
+
+    let mut position = start;
+    let mut speed_left = speed;
+    for movement in movements {
+        let (delta_translation, left) =
+            movement(position, destination, speed_left, size, collision_map);
+        position += delta_translation;
+        speed_left = left;
+    }
+
+    position
+}
+
+#[allow(clippy::type_complexity)]
 fn move_first(
     mut commands: Commands,
     mut query: Query<(
@@ -72,21 +207,38 @@ fn move_first(
         &Destination,
         &Speed,
         &MovementType,
+        &Size,
+        Option<&Path>,
         &mut Transform,
-        &MovementOrder,
+        (
+            Option<&MovementOrder>,
+            Option<&NpcMovementOrders>,
+            Option<&Target>,
+        ),
+        &ProcessingOrder,
     )>,
+    collision_map: Res<CollisionMap>,
 ) {
-    for mut entry in query.iter_mut() {
-        if entry.5 == &MovementOrder::First {
-            prv_move_entities(
-                &mut commands,
-                (entry.0, entry.1, entry.2, entry.3),
-                &mut entry.4,
-            );
-        }
+    let mut entries: Vec<_> = query
+        .iter_mut()
+        .filter(|entry| {
+            let (order, npc_orders, target) = entry.7;
+            movement_order(order, npc_orders, target) == MovementOrder::First
+        })
+        .collect();
+    entries.sort_by_key(|entry| (*entry.8, entry.0));
+
+    for mut entry in entries {
+        prv_move_entities(
+            &mut commands,
+            (entry.0, entry.1, entry.2, entry.3, entry.4, entry.5),
+            &mut entry.6,
+            &collision_map,
+        );
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn move_second(
     mut commands: Commands,
     mut query: Query<(
@@ -94,65 +246,176 @@ fn move_second(
         &Destination,
         &Speed,
         &MovementType,
+        &Size,
+        Option<&Path>,
         &mut Transform,
-        &MovementOrder,
+        (
+            Option<&MovementOrder>,
+            Option<&NpcMovementOrders>,
+            Option<&Target>,
+        ),
+        &ProcessingOrder,
     )>,
+    collision_map: Res<CollisionMap>,
 ) {
-    for mut entry in query.iter_mut() {
-        if entry.5 == &MovementOrder::Second {
-            prv_move_entities(
-                &mut commands,
-                (entry.0, entry.1, entry.2, entry.3),
-                &mut entry.4,
-            );
-        }
+    let mut entries: Vec<_> = query
+        .iter_mut()
+        .filter(|entry| {
+            let (order, npc_orders, target) = entry.7;
+            movement_order(order, npc_orders, target) == MovementOrder::Second
+        })
+        .collect();
+    entries.sort_by_key(|entry| (*entry.8, entry.0));
+
+    for mut entry in entries {
+        prv_move_entities(
+            &mut commands,
+            (entry.0, entry.1, entry.2, entry.3, entry.4, entry.5),
+            &mut entry.6,
+            &collision_map,
+        );
     }
 }
 
 fn prv_move_entities(
     commands: &mut Commands,
-    entry: (Entity, &Destination, &Speed, &MovementType),
+    entry: (
+        Entity,
+        &Destination,
+        &Speed,
+        &MovementType,
+        &Size,
+        Option<&Path>,
+    ),
     transform: &mut Transform,
+    collision_map: &CollisionMap,
 ) {
-    let (entity, destination, speed, movement_type) = entry;
-    let first_movement: fn(Vec2, Vec2, u8) -> (Vec2, u8);
-    let second_movement: fn(Vec2, Vec2, u8) -> (Vec2, u8);
-    match movement_type {
-        MovementType::CardinalFirst => {
-            first_movement = prv_move_cardinally;
-            second_movement = prv_move_diagonally;
+    let (entity, destination, speed, movement_type, size, path) = entry;
+    if *movement_type == MovementType::Pathfinding {
+        prv_move_along_path(
+            commands,
+            (entity, destination, speed, size, path),
+            transform,
+            collision_map,
+        );
+        return;
+    }
+
+    let position = moved_position(
+        transform.translation.truncate(),
+        destination.0,
+        speed.0,
+        size.0,
+        movement_type,
+        collision_map,
+    );
+    transform.translation.x = position.x;
+    transform.translation.y = position.y;
+
+    if position == destination.0 {
+        commands.entity(entity).remove::<Destination>();
+    }
+}
+
+// Helper to walk along a path from the pathfinder, only finding a new one if the destination
+// changed or the entity was moved since it last walked
+fn prv_move_along_path(
+    commands: &mut Commands,
+    entry: (Entity, &Destination, &Speed, &Size, Option<&Path>),
+    transform: &mut Transform,
+    collision_map: &CollisionMap,
+) {
+    let (entity, destination, speed, size, path) = entry;
+    let mut position = transform.translation.truncate();
+
+    let mut checkpoints = match path {
+        Some(path) if path.destination == destination.0 && path.position == position => {
+            path.checkpoints.clone()
         }
-        MovementType::DiagonalFirst => {
-            first_movement = prv_move_diagonally;
-            second_movement = prv_move_cardinally;
+        _ => find_path(position, destination.0, size.0, collision_map).into(),
+    };
+
+    for _ in 0..speed.0 {
+        let Some(checkpoint) = checkpoints.front().copied() else {
+            break;
+        };
+
+        position += (checkpoint - position).clamp(Vec2::NEG_ONE, Vec2::ONE);
+        if position == checkpoint {
+            checkpoints.pop_front();
         }
     }
+    transform.translation.x = position.x;
+    transform.translation.y = position.y;
+
+    if checkpoints.is_empty() {
+        // Either there or as close as it's going to get
+        commands.entity(entity).try_remove::<(Destination, Path)>();
+    } else {
+        commands.entity(entity).insert(Path {
+            destination: destination.0,
+            position,
+            checkpoints,
+        });
+    }
+}
+
+// Helper to step straight towards the destination like npcs do, returns the delta movement and
+// speed left. Each step is diagonal if possible, otherwise along the x axis, otherwise along the y
+// axis, and the entity just stops once none of those are possible.
+fn prv_move_dumbly(
+    start: Vec2,
+    destination: Vec2,
+    speed: u8,
+    size: u8,
+    collision_map: &CollisionMap,
+) -> (Vec2, u8) {
+    let mut delta_translation = Vec2::ZERO;
+    let mut speed_left = speed;
 
-    let (delta_translation, speed_left) =
-        first_movement(transform.translation.truncate(), destination.0, speed.0);
-    transform.translation.x += delta_translation.x;
-    transform.translation.y += delta_translation.y;
+    while speed_left > 0 {
+        let position = start + delta_translation;
+        let direction = (destination - position).clamp(Vec2::NEG_ONE, Vec2::ONE);
+        if direction == Vec2::ZERO {
+            break;
+        }
 
-    let (delta_translation, _) =
-        second_movement(transform.translation.truncate(), destination.0, speed_left);
-    transform.translation.x += delta_translation.x;
-    transform.translation.y += delta_translation.y;
+        let Some(step) = [
+            direction,
+            Vec2::new(direction.x, 0.),
+            Vec2::new(0., direction.y),
+        ]
+        .into_iter()
+        .find(|step| *step != Vec2::ZERO && collision_map.can_step(position, size, *step)) else {
+            break;
+        };
 
-    if transform.translation.truncate() == destination.0 {
-        commands.entity(entity).remove::<Destination>();
+        delta_translation += step;
+        speed_left -= 1;
     }
+
+    (delta_translation, speed_left)
 }
 
 // Helper to move diagonally, returns the delta movement and speed left
-fn prv_move_diagonally(start: Vec2, destination: Vec2, speed: u8) -> (Vec2, u8) {
+fn prv_move_diagonally(
+    start: Vec2,
+    destination: Vec2,
+    speed: u8,
+    size: u8,
+    collision_map: &CollisionMap,
+) -> (Vec2, u8) {
     let mut distance = destination - start;
 
     let mut delta_translation = Vec2::ZERO;
     let mut speed_left = speed;
 
     while distance.x != 0. && distance.y != 0. && speed_left > 0 {
-        delta_translation.x += distance.x.signum();
-        delta_translation.y += distance.y.signum();
+        let step = distance.signum();
+        if !collision_map.can_step(start + delta_translation, size, step) {
+            break;
+        }
+        delta_translation += step;
 
         distance = destination - (start + delta_translation);
         speed_left -= 1;
@@ -162,18 +425,28 @@ fn prv_move_diagonally(start: Vec2, destination: Vec2, speed: u8) -> (Vec2, u8)
 }
 
 // Helper to move cardinally, returns the delta movement and speed left
-fn prv_move_cardinally(start: Vec2, destination: Vec2, speed: u8) -> (Vec2, u8) {
+fn prv_move_cardinally(
+    start: Vec2,
+    destination: Vec2,
+    speed: u8,
+    size: u8,
+    collision_map: &CollisionMap,
+) -> (Vec2, u8) {
     let mut distance = destination - start;
 
     let mut delta_translation = Vec2::ZERO;
     let mut speed_left = speed;
 
     while (distance.x.abs() - distance.y.abs()).abs() > 0. && speed_left > 0 {
-        if distance.x.abs() > distance.y.abs() {
-            delta_translation.x += distance.x.signum();
+        let step = if distance.x.abs() > distance.y.abs() {
+            Vec2::new(distance.x.signum(), 0.)
         } else {
-            delta_translation.y += distance.y.signum();
+            Vec2::new(0., distance.y.signum())
+        };
+        if !collision_map.can_step(start + delta_translation, size, step) {
+            break;
         }
+        delta_translation += step;
 
         distance = destination - (start + delta_translation);
         speed_left -= 1;
@@ -181,3 +454,53 @@ fn prv_move_cardinally(start: Vec2, destination: Vec2, speed: u8) -> (Vec2, u8)
 
     (delta_translation, speed_left)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dumb_steps_fall_back_to_x_then_y() {
+        let destination = Vec2::new(3., 3.);
+        let mut collision_map = CollisionMap::default();
+        assert_eq!(
+            prv_move_dumbly(Vec2::ZERO, destination, 1, 1, &collision_map),
+            (Vec2::new(1., 1.), 0)
+        );
+
+        // Diagonal blocked by the tile north of it
+        collision_map.set_blocked(Vec2::new(0., 1.), true);
+        assert_eq!(
+            prv_move_dumbly(Vec2::ZERO, destination, 1, 1, &collision_map),
+            (Vec2::new(1., 0.), 0)
+        );
+
+        // Both the diagonal and x blocked by the tile east of it
+        collision_map.set_blocked(Vec2::new(0., 1.), false);
+        collision_map.set_blocked(Vec2::new(1., 0.), true);
+        assert_eq!(
+            prv_move_dumbly(Vec2::ZERO, destination, 1, 1, &collision_map),
+            (Vec2::new(0., 1.), 0)
+        );
+
+        // Nowhere left to go, so it just stands there
+        collision_map.set_blocked(Vec2::new(0., 1.), true);
+        assert_eq!(
+            prv_move_dumbly(Vec2::ZERO, destination, 2, 1, &collision_map),
+            (Vec2::ZERO, 2)
+        );
+    }
+
+    #[test]
+    fn dumb_steps_stop_at_the_destination() {
+        let collision_map = CollisionMap::default();
+        assert_eq!(
+            prv_move_dumbly(Vec2::ZERO, Vec2::new(2., 1.), 2, 1, &collision_map),
+            (Vec2::new(2., 1.), 0)
+        );
+        assert_eq!(
+            prv_move_dumbly(Vec2::ZERO, Vec2::new(1., 0.), 2, 1, &collision_map),
+            (Vec2::new(1., 0.), 1)
+        );
+    }
+}
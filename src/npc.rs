@@ -1,9 +1,15 @@
+use bevy::ecs::entity_disabling::Disabled;
+use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    attack::{AttackRange, Target, TargetUnderBehavior},
-    movement::{Destination, MovementOrder, MovementType, Speed},
-    player::Player,
+    attack::{AttackProjectile, AttackRange, AttackSpeed, MaxHit, NpcAttacks, Target},
+    game_ticks::ProcessingOrder,
+    hitpoints::{Dead, Hitpoints},
+    movement::{Destination, MovementOrder, Speed},
+    player::{Player, PlayerId},
+    scenario::{NpcSpawn, Scenario},
 };
 
 /// Default color of npcs
@@ -13,9 +19,12 @@ const NPC_COLOR: Color = Color::srgba(1., 0.5, 0.5, 0.125);
 #[require(Transform, Speed)]
 pub struct Npc {
     pub name: String,
-    // TODO: encode attacks w/ range and cds, maybe list with conditions based on range
 }
 
+/// Identifier of an npc that stays the same across app runs, unlike its `Entity`
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NpcId(pub u32);
+
 /// Size of the entity (always a square so 1 dimension)
 #[derive(Component, Debug, Clone, PartialEq)]
 pub struct Size(pub u8);
@@ -26,49 +35,151 @@ impl Default for Size {
     }
 }
 
+/// Entity of every npc spawned for the method along with the spawn it's currently set up from,
+/// including npcs since removed from the scenario. Those are disabled instead of despawned so that
+/// any actions targeting them still point to them when they come back, e.g. through undo.
+#[derive(Resource, Debug, Default)]
+pub struct NpcEntities(HashMap<NpcId, (Entity, NpcSpawn)>);
+
 #[derive(Component, Default, Debug)]
 #[allow(unused)]
 struct NpcDestinationMarker;
 
+impl NpcEntities {
+    /// Entity of the npc with the given id
+    pub fn get(&self, id: NpcId) -> Option<Entity> {
+        self.0.get(&id).map(|(entity, _)| *entity)
+    }
+
+    /// Spawn an npc along with everything needed to render it
+    pub fn spawn(
+        &mut self,
+        commands: &mut Commands,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<ColorMaterial>,
+        spawn: &NpcSpawn,
+    ) -> Entity {
+        let entity = commands
+            .spawn((
+                npc_bundle(spawn),
+                Visibility::Visible,
+                prv_npc_mesh(meshes, materials, spawn.size),
+            ))
+            .id();
+        self.0.insert(spawn.id, (entity, spawn.clone()));
+
+        entity
+    }
+
+    /// Despawn every npc, e.g. to replace them with the ones of a different method
+    pub fn despawn_all(&mut self, commands: &mut Commands) {
+        for (entity, _) in self.0.values() {
+            commands.entity(*entity).despawn();
+        }
+        self.0.clear();
+    }
+}
+
 pub struct NpcPlugin;
 
 impl Plugin for NpcPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (spawn_npc, spawn_npc_destination))
-            .add_systems(PostStartup, target_player)
+        app.insert_resource(NpcEntities::default())
+            .add_systems(Startup, (spawn_npc, spawn_npc_destination))
+            .add_systems(PostStartup, target_players)
+            .add_systems(
+                PreUpdate,
+                sync_npc_entities.run_if(resource_changed::<Scenario>),
+            )
             .add_systems(Update, draw_npc);
         #[cfg(feature = "debug")]
         app.add_systems(Update, highlight_npc_destination);
     }
 }
 
+/// Components of an npc needed by the simulation, without anything used for rendering
+pub fn npc_bundle(spawn: &NpcSpawn) -> impl Bundle {
+    // Start off with the first attack until there's a target to pick one for
+    let attack = spawn.attacks.first().cloned().unwrap_or_default();
+    (
+        Npc {
+            name: spawn.name.clone(),
+        },
+        spawn.id,
+        Transform::from_translation(spawn.position.extend(0.)),
+        Size(spawn.size),
+        Speed(spawn.speed),
+        NpcAttacks(spawn.attacks.clone()),
+        (
+            AttackRange(attack.range),
+            AttackSpeed(attack.speed),
+            MaxHit(attack.max_hit),
+            AttackProjectile(attack.projectile()),
+            attack.style,
+        ),
+        Hitpoints::new(spawn.hitpoints),
+        spawn.defence.clone(),
+        spawn.movement_type.clone(),
+        MovementOrder::First,
+        ProcessingOrder::Npc(spawn.index),
+        spawn.under_behavior.clone(),
+    )
+}
+
 fn spawn_npc(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    scenario: Res<Scenario>,
+    mut npc_entities: ResMut<NpcEntities>,
 ) {
-    let size = 5;
-    commands.spawn((
-        Npc {
-            name: String::from("test"),
-        },
-        Transform::from_translation(Vec3::new(1., 1., 0.)),
-        Visibility::Visible,
-        Size(size),
-        AttackRange(1),
-        MovementType::DiagonalFirst,
-        MovementOrder::First,
-        TargetUnderBehavior::RandomCardinal,
-        children![(
-            Mesh2d(meshes.add(Rectangle::new(size as f32, size as f32))),
-            MeshMaterial2d(materials.add(NPC_COLOR)),
-            Transform::from_translation(Vec3::new(
-                (size as f32 - 1.) / 2.,
-                (size as f32 - 1.) / 2.,
-                0.
-            )),
-        )],
-    ));
+    for spawn in scenario.npcs.iter() {
+        npc_entities.spawn(&mut commands, &mut meshes, &mut materials, spawn);
+    }
+}
+
+fn sync_npc_entities(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    scenario: Res<Scenario>,
+    mut npc_entities: ResMut<NpcEntities>,
+) {
+    // Done before anything runs in Update so that re-running the sequence uses the new npcs
+    for spawn in scenario.npcs.iter() {
+        let Some((entity, synced)) = npc_entities.0.get_mut(&spawn.id) else {
+            npc_entities.spawn(&mut commands, &mut meshes, &mut materials, spawn);
+            continue;
+        };
+
+        commands
+            .entity(*entity)
+            .remove_recursive::<Children, Disabled>();
+
+        // Only the starting position changing is handled by re-running the sequence
+        let moved = NpcSpawn {
+            position: synced.position,
+            ..spawn.clone()
+        };
+        if moved != *synced {
+            commands
+                .entity(*entity)
+                .despawn_related::<Children>()
+                .insert((
+                    npc_bundle(spawn),
+                    prv_npc_mesh(&mut meshes, &mut materials, spawn.size),
+                ));
+            *synced = spawn.clone();
+        }
+    }
+
+    for (id, (entity, _)) in npc_entities.0.iter() {
+        if !scenario.npcs.iter().any(|spawn| spawn.id == *id) {
+            commands
+                .entity(*entity)
+                .insert_recursive::<Children>(Disabled);
+        }
+    }
 }
 
 fn spawn_npc_destination(
@@ -85,7 +196,8 @@ fn spawn_npc_destination(
     ));
 }
 
-fn draw_npc(mut gizmos: Gizmos, query: Query<(&Transform, &Size), With<Npc>>) {
+#[allow(clippy::type_complexity)]
+fn draw_npc(mut gizmos: Gizmos, query: Query<(&Transform, &Size), (With<Npc>, Without<Dead>)>) {
     for (transform, size) in query.iter() {
         let mut color = NPC_COLOR;
         color.set_alpha(1.);
@@ -99,15 +211,25 @@ fn draw_npc(mut gizmos: Gizmos, query: Query<(&Transform, &Size), With<Npc>>) {
     }
 }
 
-fn target_player(
+fn target_players(
     mut commands: Commands,
-    npc_query: Query<Entity, With<Npc>>,
-    player_query: Query<Entity, With<Player>>,
+    scenario: Res<Scenario>,
+    npc_query: Query<(Entity, &NpcId), With<Npc>>,
+    player_query: Query<(Entity, &PlayerId), With<Player>>,
 ) {
-    let player = player_query.single().expect("SHOULD BE ONE PLAYER");
-
-    for npc in npc_query.iter() {
-        commands.entity(npc).insert(Target(player));
+    // Anything without a target waits until a player attacks it
+    for (npc, id) in npc_query.iter() {
+        let Some(target) = scenario
+            .npcs
+            .iter()
+            .find(|spawn| spawn.id == *id)
+            .and_then(|spawn| spawn.target)
+        else {
+            continue;
+        };
+        if let Some((player, _)) = player_query.iter().find(|(_, id)| **id == target) {
+            commands.entity(npc).insert(Target(player));
+        }
     }
 }
 
@@ -130,3 +252,19 @@ fn highlight_npc_destination(
 
     *destination_marker_visibility = Visibility::Visible;
 }
+
+fn prv_npc_mesh(
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    size: u8,
+) -> impl Bundle {
+    children![(
+        Mesh2d(meshes.add(Rectangle::new(size as f32, size as f32))),
+        MeshMaterial2d(materials.add(NPC_COLOR)),
+        Transform::from_translation(Vec3::new(
+            (size as f32 - 1.) / 2.,
+            (size as f32 - 1.) / 2.,
+            0.
+        )),
+    )]
+}
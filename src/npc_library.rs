@@ -0,0 +1,255 @@
+use std::path::{Path, PathBuf};
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::attack::{AttackCondition, NpcAttack, TargetUnderBehavior};
+use crate::combat::NpcDefence;
+use crate::movement::MovementType;
+use crate::npc::NpcId;
+use crate::player::PlayerId;
+use crate::scenario::{NpcSpawn, Scenario, default_npc_speed};
+use crate::schedule::EditingSet;
+
+/// Folder in the assets folder that npc definitions are loaded from, one definition per file
+const NPC_DEFINITION_DIR: &str = "npcs";
+
+/// Everything about a kind of npc, loaded from a data file so new npcs don't need a rebuild
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NpcDefinition {
+    pub name: String,
+    pub size: u8,
+    /// Tiles walked per game tick
+    #[serde(default = "default_npc_speed")]
+    pub speed: u8,
+    pub movement_type: MovementType,
+    /// In order of preference, see `NpcAttacks`
+    pub attacks: Vec<NpcAttack>,
+    pub under_behavior: TargetUnderBehavior,
+    /// Whether it goes after a player from the start instead of waiting to be attacked
+    #[serde(default = "prv_default_aggressive")]
+    pub aggressive: bool,
+    pub hitpoints: u16,
+    #[serde(default)]
+    pub defence: NpcDefence,
+}
+
+/// Every npc definition that could be loaded
+#[derive(Resource, Debug, Default)]
+pub struct NpcLibrary {
+    /// Sorted by name
+    pub definitions: Vec<NpcDefinition>,
+    /// Why any of the definition files couldn't be loaded
+    pub errors: Vec<String>,
+}
+
+/// Event to do something with the npc library
+#[derive(Event, Debug, Clone, PartialEq)]
+pub enum NpcLibraryEvent {
+    /// Load the definitions again to pick up changes to the files
+    Reload,
+    /// Add an npc with the definition of the given name to the scenario
+    Spawn(String),
+}
+
+impl NpcDefinition {
+    /// Spawn of an npc with this definition, going after the given player if it's aggressive
+    pub fn spawn(
+        &self,
+        id: NpcId,
+        index: u16,
+        position: Vec2,
+        player: Option<PlayerId>,
+    ) -> NpcSpawn {
+        NpcSpawn {
+            id,
+            index,
+            name: self.name.clone(),
+            size: self.size,
+            position,
+            attacks: self.attacks.clone(),
+            movement_type: self.movement_type.clone(),
+            under_behavior: self.under_behavior.clone(),
+            speed: self.speed,
+            target: player.filter(|_| self.aggressive),
+            hitpoints: self.hitpoints,
+            defence: self.defence.clone(),
+        }
+    }
+}
+
+impl NpcLibrary {
+    /// Load every definition in the given folder
+    pub fn load(dir: &Path) -> Self {
+        let mut library = Self::default();
+
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(error) => {
+                library
+                    .errors
+                    .push(format!("Failed to read {}: {error}", dir.display()));
+                return library;
+            }
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            match prv_read_definition(&path) {
+                Ok(definition) if library.get(&definition.name).is_some() => {
+                    library.errors.push(format!(
+                        "Failed to load {}: another npc is already named {}",
+                        path.display(),
+                        definition.name
+                    ));
+                }
+                Ok(definition) => library.definitions.push(definition),
+                Err(error) => library
+                    .errors
+                    .push(format!("Failed to load {}: {error}", path.display())),
+            }
+        }
+        library
+            .definitions
+            .sort_by(|left, right| left.name.cmp(&right.name));
+
+        library
+    }
+
+    /// Definition with the given name
+    pub fn get(&self, name: &str) -> Option<&NpcDefinition> {
+        self.definitions
+            .iter()
+            .find(|definition| definition.name == name)
+    }
+}
+
+pub struct NpcLibraryPlugin;
+
+impl Plugin for NpcLibraryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NpcLibrary::load(&prv_definition_dir()))
+            .add_event::<NpcLibraryEvent>()
+            .add_systems(Update, handle_library_events.in_set(EditingSet::UserInput));
+    }
+}
+
+fn handle_library_events(
+    mut library_evr: EventReader<NpcLibraryEvent>,
+    mut library: ResMut<NpcLibrary>,
+    mut scenario: ResMut<Scenario>,
+) {
+    for event in library_evr.read() {
+        match event {
+            NpcLibraryEvent::Reload => *library = NpcLibrary::load(&prv_definition_dir()),
+            NpcLibraryEvent::Spawn(name) => {
+                let Some(definition) = library.get(name) else {
+                    continue;
+                };
+
+                // Can be dragged into place from wherever it starts off
+                let id = scenario.unused_npc_id();
+                let index = scenario.unused_npc_index();
+                let position = scenario.new_npc_position();
+                let player = scenario.players.first().map(|spawn| spawn.id);
+                scenario
+                    .npcs
+                    .push(definition.spawn(id, index, position, player));
+            }
+        }
+    }
+}
+
+fn prv_definition_dir() -> PathBuf {
+    // Same place bevy loads assets from
+    FileAssetReader::get_base_path()
+        .join("assets")
+        .join(NPC_DEFINITION_DIR)
+}
+
+fn prv_read_definition(path: &Path) -> Result<NpcDefinition> {
+    let definition: NpcDefinition = ron::from_str(&std::fs::read_to_string(path)?)?;
+
+    if definition.size == 0 {
+        return Err("size has to be at least 1".into());
+    }
+    if definition.speed == 0 {
+        return Err("speed has to be at least 1".into());
+    }
+    if definition.attacks.is_empty() {
+        return Err("has no attacks".into());
+    }
+    if definition.hitpoints == 0 {
+        return Err("hitpoints have to be at least 1".into());
+    }
+    if definition
+        .attacks
+        .iter()
+        .any(|attack| matches!(attack.condition, AttackCondition::Chance(percent) if percent > 100))
+    {
+        return Err("chances have to be at most 100%".into());
+    }
+
+    Ok(definition)
+}
+
+fn prv_default_aggressive() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    #[test]
+    fn every_bundled_attack_can_be_picked() {
+        let library = NpcLibrary::load(
+            &Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("assets")
+                .join(NPC_DEFINITION_DIR),
+        );
+        assert_eq!(library.errors, Vec::<String>::new());
+        assert!(!library.definitions.is_empty());
+
+        for definition in library.definitions.iter() {
+            for index in 0..definition.attacks.len() {
+                assert!(
+                    prv_can_pick(&definition.attacks, index),
+                    "{} can never use attack {index}",
+                    definition.name
+                );
+            }
+        }
+    }
+
+    // Helper to check whether there's anywhere the target can be, and any way chances can roll,
+    // for the attack at the given index to be the first whose condition holds
+    fn prv_can_pick(attacks: &[NpcAttack], index: usize) -> bool {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut could_hold = |condition: &AttackCondition, dist: Vec2, holds: bool| match condition
+        {
+            AttackCondition::Chance(percent) => {
+                (*percent > 0 || !holds) && (*percent < 100 || holds)
+            }
+            condition => condition.holds(dist, &mut rng) == holds,
+        };
+
+        (-12..=12).any(|x| {
+            (-12..=12).any(|y| {
+                let dist = Vec2::new(x as f32, y as f32);
+                attacks[..index]
+                    .iter()
+                    .all(|attack| could_hold(&attack.condition, dist, false))
+                    && could_hold(&attacks[index].condition, dist, true)
+            })
+        })
+    }
+}
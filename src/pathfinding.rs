@@ -0,0 +1,238 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::world::CollisionMap;
+
+/// Width and height of the area searched by the pathfinder, centered on the start tile
+const SEARCH_AREA_SIZE: usize = 128;
+
+/// How far around an unreachable destination to look for the closest reachable tile
+const ALTERNATIVE_ROUTE_RANGE: i32 = 10;
+
+/// Paths at least this long are never picked as an alternative route
+const MAX_ALTERNATIVE_PATH_LENGTH: u32 = 100;
+
+/// Max number of checkpoints the game keeps from a path
+const MAX_CHECKPOINTS: usize = 25;
+
+/// Directions searched from each tile, in the same order as the game so ties are broken the same
+const DIRECTIONS: [IVec2; 8] = [
+    IVec2::new(-1, 0),
+    IVec2::new(1, 0),
+    IVec2::new(0, -1),
+    IVec2::new(0, 1),
+    IVec2::new(-1, -1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(1, 1),
+];
+
+/// Find the path from `start` to `destination` the same way the game's pathfinder does.
+///
+/// If the destination can't be reached, the path goes to the closest reachable tile to it
+/// instead. Returns the checkpoints of the path, i.e. the tiles where it changes direction
+/// followed by the last tile, or nothing if there's nowhere to move to.
+pub fn find_path(
+    start: Vec2,
+    destination: Vec2,
+    size: u8,
+    collision_map: &CollisionMap,
+) -> Vec<Vec2> {
+    let start = start.as_ivec2();
+    let destination = destination.as_ivec2();
+    let origin = start - IVec2::splat(SEARCH_AREA_SIZE as i32 / 2);
+
+    // Distance from the start to every tile reached so far, and the direction it was reached in
+    let mut distances = vec![u32::MAX; SEARCH_AREA_SIZE * SEARCH_AREA_SIZE];
+    let mut via: Vec<Option<IVec2>> = vec![None; SEARCH_AREA_SIZE * SEARCH_AREA_SIZE];
+    let index = |tile: IVec2| -> Option<usize> {
+        let local = tile - origin;
+        if local.x < 0
+            || local.y < 0
+            || local.x >= SEARCH_AREA_SIZE as i32
+            || local.y >= SEARCH_AREA_SIZE as i32
+        {
+            return None;
+        }
+
+        Some(local.y as usize * SEARCH_AREA_SIZE + local.x as usize)
+    };
+
+    let start_index = index(start).expect("START SHOULD BE IN SEARCH AREA");
+    distances[start_index] = 0;
+
+    let mut queue = VecDeque::from([start]);
+    let mut found = false;
+    while let Some(tile) = queue.pop_front() {
+        if tile == destination {
+            found = true;
+            break;
+        }
+
+        let distance = distances[index(tile).expect("QUEUED TILES SHOULD BE IN SEARCH AREA")];
+        for direction in DIRECTIONS {
+            let next = tile + direction;
+            let Some(next_index) = index(next) else {
+                continue;
+            };
+
+            if distances[next_index] != u32::MAX
+                || !collision_map.can_step(tile.as_vec2(), size, direction.as_vec2())
+            {
+                continue;
+            }
+
+            distances[next_index] = distance + 1;
+            via[next_index] = Some(direction);
+            queue.push_back(next);
+        }
+    }
+
+    let end = if found {
+        destination
+    } else {
+        // Can't get there, so go to the reachable tile closest to it instead, iterating in the
+        // same order as the game so ties are again broken the same
+        let mut best: Option<(IVec2, i32, u32)> = None;
+        for x in -ALTERNATIVE_ROUTE_RANGE..=ALTERNATIVE_ROUTE_RANGE {
+            for y in -ALTERNATIVE_ROUTE_RANGE..=ALTERNATIVE_ROUTE_RANGE {
+                let tile = destination + IVec2::new(x, y);
+                let Some(tile_index) = index(tile) else {
+                    continue;
+                };
+
+                let distance = distances[tile_index];
+                if distance >= MAX_ALTERNATIVE_PATH_LENGTH {
+                    continue;
+                }
+
+                let cost = x * x + y * y;
+                let is_better = match best {
+                    None => true,
+                    Some((_, best_cost, best_distance)) => {
+                        cost < best_cost || (cost == best_cost && distance < best_distance)
+                    }
+                };
+                if is_better {
+                    best = Some((tile, cost, distance));
+                }
+            }
+        }
+
+        let Some((tile, _, _)) = best else {
+            return Vec::new();
+        };
+        tile
+    };
+
+    // Walk backwards from the end, keeping every tile where the direction changes
+    let mut checkpoints = Vec::new();
+    let mut tile = end;
+    let mut previous_direction = None;
+    while tile != start {
+        let direction = via[index(tile).expect("PATH SHOULD BE IN SEARCH AREA")]
+            .expect("PATH TILES SHOULD HAVE BEEN REACHED");
+        if previous_direction != Some(direction) {
+            checkpoints.push(tile.as_vec2());
+            previous_direction = Some(direction);
+        }
+
+        tile -= direction;
+    }
+
+    checkpoints.reverse();
+    checkpoints.truncate(MAX_CHECKPOINTS);
+
+    checkpoints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn straight_path() {
+        let path = find_path(Vec2::ZERO, Vec2::new(5., 0.), 1, &CollisionMap::default());
+        assert_eq!(path, vec![Vec2::new(5., 0.)]);
+    }
+
+    #[test]
+    fn routes_around_walls() {
+        let mut collision_map = CollisionMap::default();
+        for y in -3..=3 {
+            collision_map.set_blocked(Vec2::new(2., y as f32), true);
+        }
+
+        let path = find_path(Vec2::ZERO, Vec2::new(4., 0.), 1, &collision_map);
+        assert_eq!(
+            path,
+            vec![
+                Vec2::new(0., -3.),
+                Vec2::new(1., -4.),
+                Vec2::new(3., -4.),
+                Vec2::new(3., -1.),
+                Vec2::new(4., 0.),
+            ]
+        );
+    }
+
+    #[test]
+    fn alternative_route_to_closest_reachable_tile() {
+        // Destination walled in on every side, the closest tiles outside the walls are two away
+        // and the one that's the shortest walk wins the tie
+        let mut collision_map = CollisionMap::default();
+        for x in 9..=11 {
+            for y in -1..=1 {
+                if (x, y) != (10, 0) {
+                    collision_map.set_blocked(Vec2::new(x as f32, y as f32), true);
+                }
+            }
+        }
+
+        let path = find_path(Vec2::ZERO, Vec2::new(10., 0.), 1, &collision_map);
+        assert_eq!(path, vec![Vec2::new(8., 0.)]);
+    }
+
+    #[test]
+    fn nowhere_to_go() {
+        let mut collision_map = CollisionMap::default();
+        for x in -1..=1 {
+            for y in -1..=1 {
+                if (x, y) != (0, 0) {
+                    collision_map.set_blocked(Vec2::new(x as f32, y as f32), true);
+                }
+            }
+        }
+
+        let path = find_path(Vec2::ZERO, Vec2::new(5., 0.), 1, &collision_map);
+        assert!(path.is_empty());
+    }
+
+    #[test]
+    fn keeps_only_the_first_checkpoints() {
+        // A corridor that zigzags through walls with gaps alternating between the top and bottom
+        let mut collision_map = CollisionMap::default();
+        for x in -1..=42 {
+            collision_map.set_blocked(Vec2::new(x as f32, 3.), true);
+            collision_map.set_blocked(Vec2::new(x as f32, -3.), true);
+        }
+        for y in -2..=2 {
+            collision_map.set_blocked(Vec2::new(-1., y as f32), true);
+        }
+        for i in 0..20 {
+            let gap = if i % 2 == 0 { 2 } else { -2 };
+            for y in (-2..=2).filter(|y| *y != gap) {
+                collision_map.set_blocked(Vec2::new((2 * i + 1) as f32, y as f32), true);
+            }
+        }
+
+        let path = find_path(Vec2::ZERO, Vec2::new(41., 0.), 1, &collision_map);
+        assert_eq!(path.len(), MAX_CHECKPOINTS);
+        assert_eq!(
+            path[..3],
+            [Vec2::new(0., 2.), Vec2::new(2., 2.), Vec2::new(2., -2.),]
+        );
+        assert_eq!(path.last(), Some(&Vec2::new(24., 2.)));
+    }
+}
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+
+use crate::game_ticks::GameTickEvent;
+use crate::player::{PlayerActionEvent, PlayerId, PlayerModifiers};
+use crate::schedule::PlaybackSet;
+use crate::sequence::ActionSequence;
+use crate::state::ToolState;
+
+/// Slowest and fastest playback speeds, relative to the game
+pub const MIN_PLAYBACK_SPEED: f32 = 0.25;
+pub const MAX_PLAYBACK_SPEED: f32 = 4.;
+
+/// Progress through the sequence while playing it back in real time
+#[derive(Resource, Debug)]
+pub struct Playback {
+    /// Tick of the sequence currently shown
+    pub tick: usize,
+    /// Last tick of the sequence, where playback stops
+    pub last_tick: usize,
+    pub playing: bool,
+    /// Multiplier on how fast game ticks go by
+    pub speed: f32,
+}
+
+/// Event to advance playback by a single game tick while paused
+#[derive(Event, Default, Debug)]
+pub struct PlaybackStepEvent;
+
+/// Event to start playback over from the beginning
+#[derive(Event, Default, Debug)]
+pub struct PlaybackRestartEvent;
+
+impl Playback {
+    /// Whether every tick of the sequence has been played
+    pub fn finished(&self) -> bool {
+        self.tick >= self.last_tick
+    }
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            tick: 0,
+            last_tick: 0,
+            playing: false,
+            speed: 1.,
+        }
+    }
+}
+
+pub struct PlaybackPlugin;
+
+impl Plugin for PlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Playback::default())
+            .add_event::<PlaybackStepEvent>()
+            .add_event::<PlaybackRestartEvent>()
+            .add_systems(OnEnter(ToolState::Playback), start_playback)
+            .add_systems(
+                Update,
+                start_playback
+                    .run_if(on_event::<PlaybackRestartEvent>)
+                    .in_set(PlaybackSet::Restart),
+            )
+            .add_systems(
+                Update,
+                send_actions
+                    .run_if(on_event::<GameTickEvent>)
+                    .in_set(PlaybackSet::SendActions),
+            );
+    }
+}
+
+fn start_playback(mut playback: ResMut<Playback>, action_sequence: Res<ActionSequence>) {
+    // Keep the speed from last time, but always start over from the beginning
+    playback.tick = 0;
+    playback.last_tick = action_sequence.len() - 1;
+    playback.playing = false;
+}
+
+fn send_actions(
+    mut playback: ResMut<Playback>,
+    action_sequence: Res<ActionSequence>,
+    mut player_action_evw: EventWriter<PlayerActionEvent>,
+    mut modifiers_query: Query<(&PlayerId, &mut PlayerModifiers)>,
+) {
+    // The game tick that just started runs the actions of the tick before it, same as catchup
+    for (id, mut modifiers) in modifiers_query.iter_mut() {
+        if let Some(lane) = action_sequence.lane(*id) {
+            *modifiers = lane.ticks[playback.tick].1.clone();
+        }
+    }
+    player_action_evw.write_batch(action_sequence.tick_events(playback.tick));
+
+    playback.tick += 1;
+}
@@ -1,14 +1,39 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::attack::{AttackRange, AttackSpeed, Target, TargetUnderBehavior, TargetedBy};
+use crate::attack::{Target, TargetUnderBehavior, TargetedBy};
+use crate::combat::CombatStats;
+use crate::game_ticks::ProcessingOrder;
+use crate::gear::{GearSetup, GearSetups};
+use crate::hitpoints::{Dead, Hitpoints};
 use crate::input::EditingResetEvent;
 use crate::movement::{Destination, MovementType, Speed};
 use crate::npc::Size;
-use crate::schedule::{EditingCatchup, EditingCatchupSet, EditingSet, FreeRoamSet};
+use crate::scenario::{PlayerSpawn, Scenario};
+use crate::schedule::{
+    EditingCatchup, EditingCatchupSet, EditingSet, FreeRoamSet, Headless, HeadlessSet, PlaybackSet,
+};
 
-/// Default colors for the player, based off of the default true tile color in runelite
-const PLAYER_COLOR: Color = Color::srgba_u8(59, 157, 155, 255);
-const PLAYER_FILL_COLOR: Color = Color::srgba_u8(30, 83, 82, 50);
+/// Outline and fill colors of each player, the first based off of the default true tile color in
+/// runelite. Any players past the last colors start over from the first.
+const PLAYER_COLORS: [(Color, Color); 4] = [
+    (
+        Color::srgba_u8(59, 157, 155, 255),
+        Color::srgba_u8(30, 83, 82, 50),
+    ),
+    (
+        Color::srgba_u8(196, 112, 214, 255),
+        Color::srgba_u8(98, 56, 107, 50),
+    ),
+    (
+        Color::srgba_u8(226, 186, 62, 255),
+        Color::srgba_u8(113, 93, 31, 50),
+    ),
+    (
+        Color::srgba_u8(92, 196, 92, 255),
+        Color::srgba_u8(46, 98, 46, 50),
+    ),
+];
 
 /// Default colors for the player's destination, based off of the default in runelite
 const DESTINATION_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
@@ -19,7 +44,11 @@ const DESTINATION_FILL_COLOR: Color = Color::srgba(0., 0., 0., 0.2);
 #[require(Transform, Speed)]
 pub struct Player;
 
-/// Marker component for the player's destination tile
+/// Identifier of a player that stays the same across app runs, unlike its `Entity`
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PlayerId(pub u32);
+
+/// Marker component for a player's destination tile, always a child of the player
 #[derive(Component, Default, Debug)]
 struct DestinationMarker;
 
@@ -30,6 +59,8 @@ pub enum PlayerAction {
     Idle,
     Move(Vec2),
     Attack(Entity),
+    /// Switch to the gear setup with the given name
+    Equip(String),
 }
 
 impl std::fmt::Display for PlayerAction {
@@ -38,32 +69,63 @@ impl std::fmt::Display for PlayerAction {
             PlayerAction::Idle => write!(f, "Idle"),
             PlayerAction::Move(dest) => write!(f, "Move: {dest}"),
             PlayerAction::Attack(target) => write!(f, "Attack {target}"),
+            PlayerAction::Equip(name) => write!(f, "Equip {name}"),
         }
     }
 }
 
-/// Modifiers to the player's behavior
-#[derive(Resource, Debug, Clone)]
+impl PlayerAction {
+    /// Whether this is a click on the game world, which overrides any clicks before it on the tick
+    pub fn is_world_click(&self) -> bool {
+        !matches!(self, PlayerAction::Equip(_))
+    }
+}
+
+/// Modifiers to a player's behavior
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlayerModifiers {
     pub run: bool,
-    pub weapon_speed: u8,
-    pub weapon_range: u8,
+}
+
+impl PlayerModifiers {
+    /// Tiles walked per game tick with these modifiers
+    pub fn speed(&self) -> u8 {
+        if self.run { 2 } else { 1 }
+    }
 }
 
 impl Default for PlayerModifiers {
     fn default() -> Self {
-        Self {
-            run: true,
-            weapon_speed: 4,
-            weapon_range: 1,
-        }
+        Self { run: true }
     }
 }
 
 /// Event to communicate a desired player action
 #[derive(Event, Default, Debug)]
 pub struct PlayerActionEvent {
+    /// Player making the input
+    pub player: PlayerId,
     pub action: PlayerAction,
+    /// Whether this comes after the other inputs already made this tick instead of replacing them
+    pub queued: bool,
+    /// Whether this is an input already in the sequence being made again as the sequence is run,
+    /// instead of a new one
+    pub replayed: bool,
+}
+
+impl PlayerActionEvent {
+    /// Events for every input of a player on a tick, in the order they're processed
+    pub fn for_tick(player: PlayerId, actions: &[PlayerAction]) -> impl Iterator<Item = Self> {
+        actions
+            .iter()
+            .enumerate()
+            .map(move |(i, action)| PlayerActionEvent {
+                player,
+                action: action.clone(),
+                queued: i > 0,
+                replayed: true,
+            })
+    }
 }
 
 enum ClickType {
@@ -75,9 +137,12 @@ pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(PlayerModifiers::default())
-            .add_event::<PlayerActionEvent>()
-            .add_systems(Startup, (spawn_player, spawn_destination))
+        app.add_event::<PlayerActionEvent>()
+            .add_systems(Startup, spawn_players)
+            .add_systems(
+                PreUpdate,
+                sync_player_entities.run_if(resource_changed::<Scenario>),
+            )
             .add_systems(
                 Update,
                 (update_action, update_modifiers, highlight_destination)
@@ -90,7 +155,12 @@ impl Plugin for PlayerPlugin {
             )
             .add_systems(
                 Update,
-                (despawn_player, spawn_player)
+                (update_action, update_modifiers, highlight_destination)
+                    .in_set(PlaybackSet::EntityUpdates),
+            )
+            .add_systems(
+                Update,
+                (despawn_players, spawn_players)
                     .chain()
                     .run_if(on_event::<EditingResetEvent>)
                     .in_set(EditingSet::EntityUpdates),
@@ -99,69 +169,119 @@ impl Plugin for PlayerPlugin {
                 EditingCatchup,
                 (update_action, update_modifiers).in_set(EditingCatchupSet::EntityUpdates),
             )
-            .add_systems(Update, draw_player);
+            .add_systems(
+                Headless,
+                (update_action, update_modifiers).in_set(HeadlessSet::EntityUpdates),
+            )
+            .add_systems(Update, draw_players);
     }
 }
 
-fn spawn_player(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-) {
+/// Components of a player needed by the simulation, without anything used for rendering
+pub fn player_bundle(spawn: &PlayerSpawn, gear: &GearSetup) -> impl Bundle {
     let mods = PlayerModifiers::default();
-    commands.spawn((
+    (
         Player,
-        Mesh2d(meshes.add(Rectangle::new(1., 1.))),
-        MeshMaterial2d(materials.add(PLAYER_FILL_COLOR)),
-        Transform::from_translation(Vec3::new(0., 0., 0.1)),
-        Speed(if mods.run { 2 } else { 1 }),
-        AttackSpeed(mods.weapon_speed),
-        AttackRange(mods.weapon_range),
-        MovementType::CardinalFirst,
+        spawn.id,
+        Transform::from_translation(spawn.position.extend(0.1)),
+        Speed(mods.speed()),
+        mods,
+        gear.bundle(&spawn.stats),
+        spawn.stats.clone(),
+        MovementType::Pathfinding,
         TargetUnderBehavior::MoveOut,
         Size(1),
-    ));
+        ProcessingOrder::Player(spawn.pid),
+        Hitpoints::new(spawn.hitpoints),
+    )
+}
+
+/// Outline color of the player with the given id
+pub fn player_color(id: PlayerId) -> Color {
+    PLAYER_COLORS[id.0 as usize % PLAYER_COLORS.len()].0
 }
 
-fn despawn_player(mut commands: Commands, mut query: Query<Entity, With<Player>>) {
-    let entity = query.single_mut().expect("SHOULD BE ONE PLAYER");
+fn spawn_players(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    scenario: Res<Scenario>,
+) {
+    for spawn in scenario.players.iter() {
+        prv_spawn_player(&mut commands, &mut meshes, &mut materials, &scenario, spawn);
+    }
+}
 
-    commands.entity(entity).despawn();
+fn despawn_players(mut commands: Commands, query: Query<Entity, With<Player>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
 }
 
-fn spawn_destination(
+fn sync_player_entities(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    scenario: Res<Scenario>,
+    query: Query<(Entity, &PlayerId), With<Player>>,
 ) {
-    commands.spawn((
-        DestinationMarker,
-        Mesh2d(meshes.add(Rectangle::new(1., 1.))),
-        MeshMaterial2d(materials.add(DESTINATION_FILL_COLOR)),
-        Transform::from_translation(Vec3::new(1., 1., 0.)),
-        Visibility::Hidden,
-    ));
+    // Done before anything runs in Update so that re-running the sequence uses the new players.
+    // Nothing refers to players by entity across edits, so removed ones can just be despawned.
+    for (entity, id) in query.iter() {
+        if scenario.player(*id).is_none() {
+            commands.entity(entity).despawn();
+        }
+    }
+    for spawn in scenario.players.iter() {
+        match query.iter().find(|(_, id)| **id == spawn.id) {
+            // PIDs can be changed at any time without having to spawn the player again
+            Some((entity, _)) => {
+                commands
+                    .entity(entity)
+                    .insert(ProcessingOrder::Player(spawn.pid));
+            }
+            None => prv_spawn_player(&mut commands, &mut meshes, &mut materials, &scenario, spawn),
+        }
+    }
 }
 
-fn draw_player(mut gizmos: Gizmos, query: Query<&Transform, With<Player>>) {
-    let transform = query.single().expect("SHOULD BE ONE PLAYER");
-    gizmos.rect_2d(
-        Isometry2d::from_translation(transform.translation.truncate()),
-        Vec2::ONE,
-        PLAYER_COLOR,
-    );
+fn draw_players(mut gizmos: Gizmos, query: Query<(&Transform, &PlayerId), With<Player>>) {
+    for (transform, id) in query.iter() {
+        gizmos.rect_2d(
+            Isometry2d::from_translation(transform.translation.truncate()),
+            Vec2::ONE,
+            player_color(*id),
+        );
+    }
 }
 
+#[allow(clippy::type_complexity)]
 fn update_action(
     mut commands: Commands,
     mut player_action_evr: EventReader<PlayerActionEvent>,
-    mut query: Query<(Entity, &Transform, Option<&TargetedBy>), With<Player>>,
+    query: Query<
+        (
+            Entity,
+            &PlayerId,
+            &Transform,
+            &CombatStats,
+            Option<&TargetedBy>,
+        ),
+        With<Player>,
+    >,
+    gear_setups: Res<GearSetups>,
+    dead_query: Query<(), With<Dead>>,
 ) {
     for player_action_event in player_action_evr.read() {
-        let (entity, transform, maybe_tb) = query.single_mut().expect("SHOULD BE ONE PLAYER");
+        let Some((entity, _, transform, stats, maybe_tb)) = query
+            .iter()
+            .find(|(_, id, ..)| **id == player_action_event.player)
+        else {
+            continue;
+        };
 
         let click_type: ClickType;
-        // Overwrite the current action with the most recent one
+        // Overwrite the current action with the most recent one, so the last click of a tick wins
         match &player_action_event.action {
             PlayerAction::Move(dest) => {
                 click_type = ClickType::YellowX;
@@ -174,6 +294,8 @@ fn update_action(
                     commands.entity(entity).try_remove::<Target>();
                 }
             }
+            // Nothing left to attack
+            PlayerAction::Attack(target) if dead_query.contains(*target) => continue,
             PlayerAction::Attack(target) => {
                 click_type = ClickType::RedX;
                 commands.entity(entity).insert(Target(*target));
@@ -184,6 +306,17 @@ fn update_action(
                 commands.entity(entity).try_remove::<Destination>();
                 commands.entity(entity).try_remove::<Target>();
             }
+            PlayerAction::Equip(name) => {
+                // Takes effect at the start of the player's turn, so an attack later this tick
+                // already uses the new gear. Any cooldown already running was set by the old gear
+                // and is left alone, same as in game.
+                if let Some(setup) = gear_setups.get(name) {
+                    commands.entity(entity).insert(setup.bundle(stats));
+                }
+
+                // Not a click on the world, so whatever the player was doing keeps going
+                continue;
+            }
         };
 
         // Set proper behavior of entities targeting the player when the player
@@ -203,40 +336,66 @@ fn update_action(
     }
 }
 
-fn update_modifiers(
-    player_modifiers: Res<PlayerModifiers>,
-    mut query: Query<(&mut Speed, &mut AttackSpeed, &mut AttackRange), With<Player>>,
-) {
-    let (mut speed, mut attack_speed, mut attack_range) =
-        query.single_mut().expect("SHOULD BE ONE PLAYER");
-
-    speed.0 = if player_modifiers.run { 2 } else { 1 };
-    attack_speed.0 = player_modifiers.weapon_speed;
-    attack_range.0 = player_modifiers.weapon_range;
+fn update_modifiers(mut query: Query<(&PlayerModifiers, &mut Speed), With<Player>>) {
+    for (modifiers, mut speed) in query.iter_mut() {
+        speed.0 = modifiers.speed();
+    }
 }
 
+#[allow(clippy::type_complexity)]
 fn highlight_destination(
     mut gizmos: Gizmos,
-    mut marker_query: Query<(&mut Transform, &mut Visibility), With<DestinationMarker>>,
-    player_query: Query<&Destination, With<Player>>,
+    mut marker_query: Query<(&ChildOf, &mut Transform, &mut Visibility), With<DestinationMarker>>,
+    player_query: Query<
+        (&Transform, Option<&Destination>),
+        (With<Player>, Without<DestinationMarker>),
+    >,
 ) {
-    let (mut destination_marker_transform, mut destination_marker_visibility) = marker_query
-        .single_mut()
-        .expect("SHOULD BE ONE DESTINATION MARKER");
-
-    // Only highlight the destination if there is one
-    let Ok(Destination(location)) = player_query.single() else {
-        *destination_marker_visibility = Visibility::Hidden;
-        return;
-    };
-    destination_marker_transform.translation.x = location.x;
-    destination_marker_transform.translation.y = location.y;
-
-    gizmos.rect_2d(
-        Isometry2d::from_translation(destination_marker_transform.translation.truncate()),
-        Vec2::ONE,
-        DESTINATION_COLOR,
-    );
-
-    *destination_marker_visibility = Visibility::Visible;
+    for (child_of, mut marker_transform, mut marker_visibility) in marker_query.iter_mut() {
+        // Only highlight the destination if there is one
+        let Ok((player_transform, Some(Destination(location)))) =
+            player_query.get(child_of.parent())
+        else {
+            *marker_visibility = Visibility::Hidden;
+            continue;
+        };
+
+        // Relative to the player since it's a child of it
+        let offset = location - player_transform.translation.truncate();
+        marker_transform.translation.x = offset.x;
+        marker_transform.translation.y = offset.y;
+
+        gizmos.rect_2d(
+            Isometry2d::from_translation(*location),
+            Vec2::ONE,
+            DESTINATION_COLOR,
+        );
+
+        *marker_visibility = Visibility::Visible;
+    }
+}
+
+// Helper to spawn a player along with everything needed to render it
+fn prv_spawn_player(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    scenario: &Scenario,
+    spawn: &PlayerSpawn,
+) {
+    let (_, fill_color) = PLAYER_COLORS[spawn.id.0 as usize % PLAYER_COLORS.len()];
+    let gear = scenario.gear.get_or_unarmed(spawn.starting_gear.as_deref());
+    commands.spawn((
+        player_bundle(spawn, &gear),
+        Mesh2d(meshes.add(Rectangle::new(1., 1.))),
+        MeshMaterial2d(materials.add(fill_color)),
+        children![(
+            DestinationMarker,
+            Mesh2d(meshes.add(Rectangle::new(1., 1.))),
+            MeshMaterial2d(materials.add(DESTINATION_FILL_COLOR)),
+            // Just under the player
+            Transform::from_translation(Vec3::new(0., 0., -0.1)),
+            Visibility::Hidden,
+        )],
+    ));
 }
@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+
+use crate::{
+    attack::{AttackStyle, RecentAttacks, attack_color},
+    game_ticks::GameTickEvent,
+    npc::Size,
+    player::Player,
+    schedule::{
+        EditingCatchup, EditingCatchupSet, FreeRoamSet, Headless, HeadlessSet, PlaybackSet,
+    },
+};
+
+/// Radius of the circle projectiles are drawn as
+const PROJECTILE_RADIUS: f32 = 0.2;
+
+/// Ranged or magic attack on its way to the target, hitting once it runs out of ticks
+#[derive(Component, Debug, Clone, PartialEq)]
+#[require(Transform)]
+pub struct Projectile {
+    pub attacker: Entity,
+    pub target: Entity,
+    /// Game ticks from the attack until it hits
+    pub delay: u8,
+    /// Game ticks left until it hits
+    pub ticks_left: u8,
+    pub damage: u16,
+}
+
+/// An attack landing on its target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    pub attacker: Entity,
+    pub target: Entity,
+    /// Game ticks since the attack was made
+    pub delay: u8,
+    pub damage: u16,
+}
+
+/// Hits that landed on the most recent game tick
+#[derive(Resource, Debug, Default)]
+pub struct RecentHits(pub Vec<Hit>);
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RecentHits::default())
+            .add_systems(
+                Update,
+                update_projectiles
+                    .run_if(on_event::<GameTickEvent>)
+                    .in_set(FreeRoamSet::Hits),
+            )
+            .add_systems(
+                EditingCatchup,
+                update_projectiles
+                    .run_if(on_event::<GameTickEvent>)
+                    .in_set(EditingCatchupSet::Hits),
+            )
+            .add_systems(
+                Update,
+                update_projectiles
+                    .run_if(on_event::<GameTickEvent>)
+                    .in_set(PlaybackSet::Hits),
+            )
+            .add_systems(
+                Headless,
+                update_projectiles
+                    .run_if(on_event::<GameTickEvent>)
+                    .in_set(HeadlessSet::Hits),
+            )
+            .add_systems(Update, draw_projectiles);
+    }
+}
+
+fn update_projectiles(
+    mut commands: Commands,
+    mut projectile_query: Query<(Entity, &mut Projectile, &mut Transform)>,
+    entity_query: Query<(&Transform, &Size), Without<Projectile>>,
+    recent_attacks: Res<RecentAttacks>,
+    mut recent_hits: ResMut<RecentHits>,
+) {
+    recent_hits.0.clear();
+
+    // Anything already on its way gets closer, hitting once it runs out of ticks
+    for (entity, mut projectile, mut transform) in projectile_query.iter_mut() {
+        // Nothing to hit if the target went away
+        let Some(target_center) = prv_center(&entity_query, projectile.target) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+
+        projectile.ticks_left = projectile.ticks_left.saturating_sub(1);
+        if projectile.ticks_left == 0 {
+            recent_hits.0.push(Hit {
+                attacker: projectile.attacker,
+                target: projectile.target,
+                delay: projectile.delay,
+                damage: projectile.damage,
+            });
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        // Cover an even share of whatever distance is left every tick
+        let position = transform.translation.truncate();
+        let step = (target_center - position) / (projectile.ticks_left + 1) as f32;
+        transform.translation += step.extend(0.);
+    }
+
+    for attack in recent_attacks.0.iter() {
+        if attack.hit_delay == 0 {
+            recent_hits.0.push(Hit {
+                attacker: attack.attacker,
+                target: attack.target,
+                delay: 0,
+                damage: attack.damage,
+            });
+            continue;
+        }
+
+        let Some(origin) = prv_center(&entity_query, attack.attacker) else {
+            continue;
+        };
+        commands.spawn((
+            Projectile {
+                attacker: attack.attacker,
+                target: attack.target,
+                delay: attack.hit_delay,
+                ticks_left: attack.hit_delay,
+                damage: attack.damage,
+            },
+            Transform::from_translation(origin.extend(0.)),
+        ));
+    }
+}
+
+fn draw_projectiles(
+    mut gizmos: Gizmos,
+    projectile_query: Query<(&Projectile, &Transform)>,
+    attacker_query: Query<(Has<Player>, Option<&AttackStyle>)>,
+) {
+    for (projectile, transform) in projectile_query.iter() {
+        let Ok((is_player, style)) = attacker_query.get(projectile.attacker) else {
+            continue;
+        };
+
+        gizmos.circle_2d(
+            Isometry2d::from_translation(transform.translation.truncate()),
+            PROJECTILE_RADIUS,
+            attack_color(is_player, style),
+        );
+    }
+}
+
+// Helper to find the center of an entity, taking its size into account
+fn prv_center(
+    entity_query: &Query<(&Transform, &Size), Without<Projectile>>,
+    entity: Entity,
+) -> Option<Vec2> {
+    let (transform, size) = entity_query.get(entity).ok()?;
+
+    Some(transform.translation.truncate() + (size.0 as f32 / 2. - 0.5))
+}
@@ -0,0 +1,297 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::attack::{NpcAttack, TargetUnderBehavior};
+use crate::combat::{CombatStats, NpcDefence};
+use crate::gear::GearSetups;
+use crate::movement::MovementType;
+use crate::npc::NpcId;
+use crate::player::PlayerId;
+use crate::rng::SimulationRng;
+use crate::schedule::EditingSet;
+use crate::snapshot::SequenceSnapshots;
+use crate::state::EditingState;
+use crate::world::CollisionMap;
+
+/// Starting setup of a method
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    /// There's always at least one player
+    pub players: Vec<PlayerSpawn>,
+    pub npcs: Vec<NpcSpawn>,
+    #[serde(default)]
+    pub collision: CollisionMap,
+    /// Seed of all randomness while running the method
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub gear: GearSetups,
+}
+
+/// Everything needed to spawn a player at the start of a method
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerSpawn {
+    pub id: PlayerId,
+    pub name: String,
+    pub position: Vec2,
+    /// Name of the gear setup the player starts in, unarmed if there isn't one
+    #[serde(default)]
+    pub starting_gear: Option<String>,
+    /// Decides the order players are processed in, see `ProcessingOrder`
+    #[serde(default)]
+    pub pid: u16,
+    #[serde(default = "prv_default_player_hitpoints")]
+    pub hitpoints: u16,
+    #[serde(default)]
+    pub stats: CombatStats,
+}
+
+/// Everything needed to spawn an npc at the start of a method
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NpcSpawn {
+    pub id: NpcId,
+    pub name: String,
+    pub size: u8,
+    pub position: Vec2,
+    /// In order of preference, see `NpcAttacks`
+    #[serde(default = "prv_default_attacks")]
+    pub attacks: Vec<NpcAttack>,
+    pub movement_type: MovementType,
+    pub under_behavior: TargetUnderBehavior,
+    /// Tiles walked per game tick
+    #[serde(default = "default_npc_speed")]
+    pub speed: u8,
+    /// Player it goes after from the start, otherwise it waits until it's attacked
+    #[serde(default)]
+    pub target: Option<PlayerId>,
+    /// Decides the order npcs are processed in, see `ProcessingOrder`
+    #[serde(default)]
+    pub index: u16,
+    #[serde(default = "prv_default_npc_hitpoints")]
+    pub hitpoints: u16,
+    #[serde(default)]
+    pub defence: NpcDefence,
+}
+
+/// Something in the scenario with a starting position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScenarioEntity {
+    Player(PlayerId),
+    Npc(NpcId),
+}
+
+/// What clicking on the grid does while editing a method
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub enum ScenarioTool {
+    /// Clicks are player actions
+    #[default]
+    Actions,
+    /// Drag things around to place their starting positions
+    PlaceStart,
+    /// Click or drag to toggle whether tiles are blocked
+    PaintBlocked,
+}
+
+impl Scenario {
+    /// Starting SW tile of something in the scenario
+    pub fn position(&self, entity: ScenarioEntity) -> Option<Vec2> {
+        match entity {
+            ScenarioEntity::Player(id) => self.player(id).map(|spawn| spawn.position),
+            ScenarioEntity::Npc(id) => self
+                .npcs
+                .iter()
+                .find(|spawn| spawn.id == id)
+                .map(|spawn| spawn.position),
+        }
+    }
+
+    /// Set the starting SW tile of something in the scenario
+    pub fn set_position(&mut self, entity: ScenarioEntity, position: Vec2) {
+        match entity {
+            ScenarioEntity::Player(id) => {
+                if let Some(spawn) = self.players.iter_mut().find(|spawn| spawn.id == id) {
+                    spawn.position = position;
+                }
+            }
+            ScenarioEntity::Npc(id) => {
+                if let Some(spawn) = self.npcs.iter_mut().find(|spawn| spawn.id == id) {
+                    spawn.position = position;
+                }
+            }
+        }
+    }
+
+    /// What is on the given tile at the start, preferring players since they're drawn on top
+    pub fn entity_at(&self, tile: Vec2) -> Option<ScenarioEntity> {
+        if let Some(spawn) = self.players.iter().find(|spawn| spawn.position == tile) {
+            return Some(ScenarioEntity::Player(spawn.id));
+        }
+
+        self.npcs
+            .iter()
+            .find(|spawn| {
+                spawn.position.x <= tile.x
+                    && tile.x <= spawn.position.x + (spawn.size as f32 - 1.)
+                    && spawn.position.y <= tile.y
+                    && tile.y <= spawn.position.y + (spawn.size as f32 - 1.)
+            })
+            .map(|spawn| ScenarioEntity::Npc(spawn.id))
+    }
+
+    /// Player with the given id
+    pub fn player(&self, id: PlayerId) -> Option<&PlayerSpawn> {
+        self.players.iter().find(|spawn| spawn.id == id)
+    }
+
+    /// Where new npcs are put, close to the first player so they're easy to find
+    pub fn new_npc_position(&self) -> Vec2 {
+        self.players
+            .first()
+            .map(|spawn| spawn.position)
+            .unwrap_or_default()
+            + Vec2::splat(2.)
+    }
+
+    /// Id for a new player that isn't taken by any of the others yet
+    pub fn unused_player_id(&self) -> PlayerId {
+        PlayerId(
+            self.players
+                .iter()
+                .map(|spawn| spawn.id.0 + 1)
+                .max()
+                .unwrap_or(0),
+        )
+    }
+
+    /// PID for a new player, processed after all of the others
+    pub fn unused_pid(&self) -> u16 {
+        self.players
+            .iter()
+            .map(|spawn| spawn.pid + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Index for a new npc, processed after all of the others
+    pub fn unused_npc_index(&self) -> u16 {
+        self.npcs
+            .iter()
+            .map(|spawn| spawn.index + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Flip the order players are processed in, to see how the method plays out with the other
+    /// PID outcome
+    pub fn reverse_pids(&mut self) {
+        let mut by_pid: Vec<usize> = (0..self.players.len()).collect();
+        by_pid.sort_by_key(|i| self.players[*i].pid);
+        let pids: Vec<u16> = by_pid.iter().map(|i| self.players[*i].pid).collect();
+        for (i, pid) in by_pid.into_iter().zip(pids.into_iter().rev()) {
+            self.players[i].pid = pid;
+        }
+    }
+
+    /// Id for a new npc that isn't taken by any of the others yet
+    pub fn unused_npc_id(&self) -> NpcId {
+        NpcId(
+            self.npcs
+                .iter()
+                .map(|spawn| spawn.id.0 + 1)
+                .max()
+                .unwrap_or(0),
+        )
+    }
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            players: vec![PlayerSpawn::default()],
+            npcs: vec![NpcSpawn::default()],
+            collision: CollisionMap::default(),
+            seed: 0,
+            gear: GearSetups::default(),
+        }
+    }
+}
+
+impl Default for PlayerSpawn {
+    fn default() -> Self {
+        Self {
+            id: PlayerId(0),
+            name: String::from("Player"),
+            position: Vec2::ZERO,
+            starting_gear: None,
+            pid: 0,
+            hitpoints: prv_default_player_hitpoints(),
+            stats: CombatStats::default(),
+        }
+    }
+}
+
+impl Default for NpcSpawn {
+    fn default() -> Self {
+        Self {
+            id: NpcId(0),
+            name: String::from("test"),
+            size: 5,
+            position: Vec2::ONE,
+            attacks: prv_default_attacks(),
+            movement_type: MovementType::Dumb,
+            under_behavior: TargetUnderBehavior::RandomCardinal,
+            speed: default_npc_speed(),
+            target: Some(PlayerId(0)),
+            index: 0,
+            hitpoints: prv_default_npc_hitpoints(),
+            defence: NpcDefence::default(),
+        }
+    }
+}
+
+pub struct ScenarioPlugin;
+
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Scenario::default())
+            .insert_resource(SimulationRng::new(Scenario::default().seed))
+            .insert_resource(ScenarioTool::default())
+            .add_systems(
+                Update,
+                restart_sequence
+                    .run_if(resource_changed::<Scenario>)
+                    .in_set(EditingSet::SequenceUpdates),
+            );
+    }
+}
+
+/// Run condition for when clicks on the grid edit the scenario instead of being player actions
+pub fn editing_scenario(tool: Res<ScenarioTool>) -> bool {
+    *tool != ScenarioTool::Actions
+}
+
+/// Tiles an npc walks per game tick when nothing says otherwise
+pub fn default_npc_speed() -> u8 {
+    1
+}
+
+fn restart_sequence(
+    mut next_state: ResMut<NextState<EditingState>>,
+    mut snapshots: ResMut<SequenceSnapshots>,
+) {
+    // Re-run the sequence from the start so the change in setup is reflected everywhere
+    snapshots.clear();
+    next_state.set(EditingState::Reconciliation);
+}
+
+fn prv_default_attacks() -> Vec<NpcAttack> {
+    vec![NpcAttack::default()]
+}
+
+fn prv_default_player_hitpoints() -> u16 {
+    99
+}
+
+fn prv_default_npc_hitpoints() -> u16 {
+    100
+}
@@ -5,6 +5,10 @@ use crate::state::{EditingState, ToolState};
 #[derive(ScheduleLabel, Debug, Hash, PartialEq, Eq, Clone)]
 pub struct EditingCatchup;
 
+/// Schedule for a single game tick of a headless simulation, run manually by the simulation
+#[derive(ScheduleLabel, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct Headless;
+
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
 pub enum FreeRoamSet {
     UserInput,
@@ -16,6 +20,8 @@ pub enum FreeRoamSet {
     SecondAttackChecks,
     SecondMovement,
     Attacks,
+    Hits,
+    Damage,
 }
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
@@ -25,6 +31,7 @@ pub enum EditingSet {
     EntityUpdates,
     AttackChecks,
     SequenceUpdates,
+    History,
 }
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
@@ -44,9 +51,41 @@ pub enum EditingCatchupSet {
     SecondAttackChecks,
     SecondMovement,
     Attacks,
+    Hits,
+    Damage,
     Transition,
 }
 
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum PlaybackSet {
+    Restart,
+    GameTick,
+    SendActions,
+    EntityUpdates,
+    SimultaneousAttackChecks,
+    FirstAttackChecks,
+    FirstMovement,
+    SecondAttackChecks,
+    SecondMovement,
+    Attacks,
+    Hits,
+    Damage,
+}
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum HeadlessSet {
+    EntityUpdates,
+    GameTick,
+    SimultaneousAttackChecks,
+    FirstAttackChecks,
+    FirstMovement,
+    SecondAttackChecks,
+    SecondMovement,
+    Attacks,
+    Hits,
+    Damage,
+}
+
 pub struct SchedulePlugin;
 
 impl Plugin for SchedulePlugin {
@@ -63,6 +102,8 @@ impl Plugin for SchedulePlugin {
                 FreeRoamSet::SecondAttackChecks,
                 FreeRoamSet::SecondMovement,
                 FreeRoamSet::Attacks,
+                FreeRoamSet::Hits,
+                FreeRoamSet::Damage,
             )
                 .chain()
                 .run_if(in_state(ToolState::FreeRoam)),
@@ -75,6 +116,7 @@ impl Plugin for SchedulePlugin {
                 EditingSet::EntityUpdates,
                 EditingSet::AttackChecks,
                 EditingSet::SequenceUpdates,
+                EditingSet::History,
             )
                 .chain()
                 .run_if(in_state(ToolState::Editing))
@@ -102,11 +144,48 @@ impl Plugin for SchedulePlugin {
                 EditingCatchupSet::SecondAttackChecks,
                 EditingCatchupSet::SecondMovement,
                 EditingCatchupSet::Attacks,
+                EditingCatchupSet::Hits,
+                EditingCatchupSet::Damage,
                 EditingCatchupSet::Transition,
             )
                 .chain()
                 .run_if(in_state(ToolState::Editing))
                 .run_if(in_state(EditingState::Catchup)),
+        )
+        .configure_sets(
+            Update,
+            (
+                PlaybackSet::Restart,
+                PlaybackSet::GameTick,
+                PlaybackSet::SendActions,
+                PlaybackSet::EntityUpdates,
+                PlaybackSet::SimultaneousAttackChecks,
+                PlaybackSet::FirstAttackChecks,
+                PlaybackSet::FirstMovement,
+                PlaybackSet::SecondAttackChecks,
+                PlaybackSet::SecondMovement,
+                PlaybackSet::Attacks,
+                PlaybackSet::Hits,
+                PlaybackSet::Damage,
+            )
+                .chain()
+                .run_if(in_state(ToolState::Playback)),
+        )
+        .configure_sets(
+            Headless,
+            (
+                HeadlessSet::EntityUpdates,
+                HeadlessSet::GameTick,
+                HeadlessSet::SimultaneousAttackChecks,
+                HeadlessSet::FirstAttackChecks,
+                HeadlessSet::FirstMovement,
+                HeadlessSet::SecondAttackChecks,
+                HeadlessSet::SecondMovement,
+                HeadlessSet::Attacks,
+                HeadlessSet::Hits,
+                HeadlessSet::Damage,
+            )
+                .chain(),
         );
     }
 }
@@ -1,27 +1,172 @@
 use bevy::prelude::*;
 
-use crate::attack::Target;
+use crate::attack::{AttackRng, AttackStatus, Cooldown, RecentAttacks, Target};
+use crate::history::RecordEditEvent;
+use crate::hitpoints::{Dead, RecentDeaths};
 use crate::input::EditingResetEvent;
-use crate::npc::Npc;
-use crate::player::{Player, PlayerAction, PlayerActionEvent, PlayerModifiers};
-use crate::schedule::{EditingCatchup, EditingCatchupChecksSet, EditingCatchupSet, EditingSet};
+use crate::movement::{Destination, NpcMovementOrders, Path, Speed};
+use crate::npc::{Npc, NpcId, npc_bundle};
+use crate::playback::PlaybackRestartEvent;
+use crate::player::{
+    Player, PlayerAction, PlayerActionEvent, PlayerId, PlayerModifiers, player_bundle,
+};
+use crate::projectile::{Projectile, RecentHits};
+use crate::rng::SimulationRng;
+use crate::scenario::Scenario;
+use crate::schedule::{
+    EditingCatchup, EditingCatchupChecksSet, EditingCatchupSet, EditingSet, PlaybackSet,
+};
+use crate::snapshot::{SequenceSnapshots, record_snapshot};
 use crate::state::{EditingState, ToolState};
 
+/// Components players and npcs only pick up while the sequence runs
+type SequenceState = (
+    Destination,
+    Path,
+    Target,
+    AttackStatus,
+    NpcMovementOrders,
+    AttackRng,
+    Dead,
+);
+
 #[derive(Resource, Debug)]
 pub struct ActionSequence {
     pub target_tick: usize,
     pub current_tick: usize,
-    pub sequence: Vec<(PlayerAction, PlayerModifiers)>,
+    /// Lane of every player in the scenario, all with the same number of ticks
+    pub lanes: Vec<SequenceLane>,
+    /// Player whose lane is being edited
+    pub selected_player: PlayerId,
 }
 
-impl Default for ActionSequence {
-    fn default() -> Self {
+/// Everything a single player does over the sequence
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceLane {
+    pub player: PlayerId,
+    /// Inputs of every tick in the order they're made, there's always at least one per tick
+    pub ticks: Vec<(Vec<PlayerAction>, PlayerModifiers)>,
+}
+
+/// Event to change which ticks are in the sequence, tick changes apply to every lane while input
+/// changes only apply to the selected player's lane
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub enum SequenceEditEvent {
+    /// Insert a copy of the tick before it
+    InsertBefore(usize),
+    /// Insert a copy of the tick after it
+    InsertAfter(usize),
+    Delete(usize),
+    /// Insert a copy of every tick in the inclusive range right after it
+    DuplicateRange(usize, usize),
+    /// Remove the input at the index from a tick
+    RemoveInput(usize, usize),
+    /// Swap the input at the index with the one made before it on the same tick
+    MoveInputEarlier(usize, usize),
+    /// Swap the input at the index with the one made after it on the same tick
+    MoveInputLater(usize, usize),
+}
+
+impl ActionSequence {
+    /// A single idle tick for each of the given players
+    pub fn new(players: impl IntoIterator<Item = PlayerId>) -> Self {
+        let lanes: Vec<SequenceLane> = players
+            .into_iter()
+            .map(|player| SequenceLane::idle(player, 1))
+            .collect();
         Self {
-            target_tick: Default::default(),
-            current_tick: Default::default(),
-            sequence: vec![(PlayerAction::Idle, PlayerModifiers::default())],
+            target_tick: 0,
+            current_tick: 0,
+            selected_player: lanes.first().map(|lane| lane.player).unwrap_or_default(),
+            lanes,
         }
     }
+
+    /// Number of ticks in the sequence
+    pub fn len(&self) -> usize {
+        self.lanes.first().map_or(0, |lane| lane.ticks.len())
+    }
+
+    /// Whether there aren't any ticks at all, which only happens without any players
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lane of the given player
+    pub fn lane(&self, player: PlayerId) -> Option<&SequenceLane> {
+        self.lanes.iter().find(|lane| lane.player == player)
+    }
+
+    /// Lane of the given player
+    pub fn lane_mut(&mut self, player: PlayerId) -> Option<&mut SequenceLane> {
+        self.lanes.iter_mut().find(|lane| lane.player == player)
+    }
+
+    /// Lane of the player being edited
+    pub fn selected_lane(&self) -> &SequenceLane {
+        self.lane(self.selected_player)
+            .or(self.lanes.first())
+            .expect("SHOULD ALWAYS HAVE A LANE")
+    }
+
+    /// Lane of the player being edited
+    pub fn selected_lane_mut(&mut self) -> &mut SequenceLane {
+        let selected = self.selected_player;
+        let index = self
+            .lanes
+            .iter()
+            .position(|lane| lane.player == selected)
+            .unwrap_or(0);
+        &mut self.lanes[index]
+    }
+
+    /// Add an idle lane for a new player
+    pub fn add_lane(&mut self, player: PlayerId) {
+        let len = usize::max(self.len(), 1);
+        self.lanes.push(SequenceLane::idle(player, len));
+    }
+
+    /// Remove the lane of a player that's no longer around, picking another to edit if needed
+    pub fn remove_lane(&mut self, player: PlayerId) {
+        self.lanes.retain(|lane| lane.player != player);
+        if self.selected_player == player {
+            self.selected_player = self
+                .lanes
+                .first()
+                .map(|lane| lane.player)
+                .unwrap_or_default();
+        }
+    }
+
+    /// Events for the inputs of every player on a tick
+    pub fn tick_events(&self, tick: usize) -> impl Iterator<Item = PlayerActionEvent> {
+        self.lanes
+            .iter()
+            .flat_map(move |lane| PlayerActionEvent::for_tick(lane.player, &lane.ticks[tick].0))
+    }
+
+    /// Run a change on the ticks of every lane at once
+    fn edit_ticks(&mut self, edit: impl Fn(&mut Vec<(Vec<PlayerAction>, PlayerModifiers)>)) {
+        for lane in self.lanes.iter_mut() {
+            edit(&mut lane.ticks);
+        }
+    }
+}
+
+impl SequenceLane {
+    /// Lane of a player that does nothing for the given number of ticks
+    pub fn idle(player: PlayerId, len: usize) -> Self {
+        Self {
+            player,
+            ticks: vec![(vec![PlayerAction::Idle], PlayerModifiers::default()); len],
+        }
+    }
+}
+
+impl Default for ActionSequence {
+    fn default() -> Self {
+        Self::new([PlayerId::default()])
+    }
 }
 
 pub struct SequencePlugin;
@@ -29,6 +174,8 @@ pub struct SequencePlugin;
 impl Plugin for SequencePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(ActionSequence::default())
+            .insert_resource(SequenceSnapshots::default())
+            .add_event::<SequenceEditEvent>()
             .add_systems(
                 Update,
                 (setup_sequence, run_editing_catchup)
@@ -36,13 +183,25 @@ impl Plugin for SequencePlugin {
                     .run_if(in_state(EditingState::Reconciliation)),
             )
             .add_systems(OnEnter(ToolState::Editing), setup_sequence)
+            .add_systems(OnEnter(ToolState::Playback), setup_sequence)
+            .add_systems(
+                Update,
+                setup_sequence
+                    .run_if(on_event::<PlaybackRestartEvent>)
+                    .in_set(PlaybackSet::Restart),
+            )
             .add_systems(
                 Update,
                 check_target_tick.in_set(EditingSet::ReconcileSequenceLocation),
             )
             .add_systems(
                 Update,
-                (update_current_action, update_current_modifiers)
+                (
+                    update_current_action,
+                    update_current_modifiers,
+                    edit_sequence,
+                )
+                    .chain()
                     .in_set(EditingSet::SequenceUpdates),
             )
             .add_systems(
@@ -54,7 +213,9 @@ impl Plugin for SequencePlugin {
             )
             .add_systems(
                 EditingCatchup,
-                check_redundancies.in_set(EditingCatchupChecksSet::SequenceChecks),
+                (record_snapshot, check_redundancies)
+                    .chain()
+                    .in_set(EditingCatchupChecksSet::SequenceChecks),
             )
             .add_systems(
                 EditingCatchup,
@@ -71,35 +232,115 @@ impl Plugin for SequencePlugin {
     }
 }
 
-#[allow(clippy::type_complexity)]
+/// Put the given players and npcs back the way the scenario spawns them, without anything they
+/// picked up while the sequence ran
+pub fn reset_to_spawn(
+    commands: &mut Commands,
+    scenario: &Scenario,
+    players: &[(Entity, PlayerId)],
+    npcs: &[(Entity, NpcId)],
+) {
+    for (player, id) in players.iter() {
+        let Some(spawn) = scenario.player(*id) else {
+            continue;
+        };
+
+        // Back into the starting gear and at full health, rolling like it never attacked
+        let gear = scenario.gear.get_or_unarmed(spawn.starting_gear.as_deref());
+        commands
+            .entity(*player)
+            .insert((player_bundle(spawn, &gear), Cooldown(0)))
+            .try_remove::<SequenceState>();
+    }
+
+    for (npc, id) in npcs.iter() {
+        let Some(spawn) = scenario.npcs.iter().find(|spawn| spawn.id == *id) else {
+            continue;
+        };
+
+        let mut npc = commands.entity(*npc);
+        npc.insert((npc_bundle(spawn), Cooldown(0)))
+            .try_remove::<SequenceState>();
+        // Anything without a target waits until a player attacks it
+        let target = spawn
+            .target
+            .and_then(|target| players.iter().find(|(_, id)| *id == target));
+        if let Some((player, _)) = target {
+            npc.insert(Target(*player));
+        }
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 fn setup_sequence(
     mut commands: Commands,
     mut action_sequence: ResMut<ActionSequence>,
-    mut player_query: Query<(Entity, &mut Transform), With<Player>>,
-    mut npc_query: Query<(Entity, &mut Transform), (With<Npc>, Without<Player>)>,
+    snapshots: Res<SequenceSnapshots>,
+    mut recent_attacks: ResMut<RecentAttacks>,
+    mut recent_hits: ResMut<RecentHits>,
+    mut recent_deaths: ResMut<RecentDeaths>,
+    mut rng: ResMut<SimulationRng>,
+    scenario: Res<Scenario>,
+    tool_state: Res<State<ToolState>>,
+    player_query: Query<(Entity, &PlayerId), With<Player>>,
+    npc_query: Query<(Entity, &NpcId), With<Npc>>,
+    projectile_query: Query<Entity, With<Projectile>>,
     mut player_action_evw: EventWriter<PlayerActionEvent>,
 ) {
     // Not .expect() ing here because right now bevy runs the StateTransition schedule
     // before PreStartup (i.e. the very first schedule run in the whole app),
     // so this will be run before any of the startup systems get a chance to run.
-    let Ok((player, mut player_transform)) = player_query.single_mut() else {
+    if player_query.is_empty() {
         return;
+    }
+
+    // Pick up from the latest tick that's still known instead of going all the way back to the
+    // start, except for playback which always plays the whole sequence
+    let tick = match tool_state.get() {
+        ToolState::Playback => 0,
+        _ => action_sequence.target_tick,
     };
-    // TODO: save a starting location
-    player_transform.translation.x = 0.;
-    player_transform.translation.y = 0.;
+    // Whatever is still in the air belongs to wherever the sequence was before
+    for projectile in projectile_query.iter() {
+        commands.entity(projectile).despawn();
+    }
 
-    player_action_evw.write(PlayerActionEvent {
-        action: action_sequence.sequence[0].0.clone(),
-    });
+    if let Some((tick, snapshot)) = snapshots.latest(tick) {
+        snapshot.restore(
+            &mut commands,
+            &mut rng,
+            (&mut recent_attacks, &mut recent_hits, &mut recent_deaths),
+        );
 
-    for (npc, mut npc_transform) in npc_query.iter_mut() {
-        commands.entity(npc).insert(Target(player));
+        player_action_evw.write_batch(action_sequence.tick_events(tick));
+        action_sequence.current_tick = tick;
+        return;
+    }
 
-        // TODO: have a starting location saved from some config
-        npc_transform.translation.x = 1.;
-        npc_transform.translation.y = 1.;
+    let players: Vec<(Entity, PlayerId)> = player_query
+        .iter()
+        .map(|(player, id)| (player, *id))
+        .collect();
+    let npcs: Vec<(Entity, NpcId)> = npc_query.iter().map(|(npc, id)| (npc, *id)).collect();
+    reset_to_spawn(&mut commands, &scenario, &players, &npcs);
+
+    // Running the way the first tick says, like it does for every tick after
+    for (player, id) in players.iter() {
+        if let Some(lane) = action_sequence.lane(*id) {
+            let modifiers = &lane.ticks[0].1;
+            commands
+                .entity(*player)
+                .insert((modifiers.clone(), Speed(modifiers.speed())));
+        }
     }
+    player_action_evw.write_batch(action_sequence.tick_events(0));
+
+    recent_attacks.0.clear();
+    recent_hits.0.clear();
+    recent_deaths.0.clear();
+
+    // Same randomness every time the sequence is run
+    *rng = SimulationRng::new(scenario.seed);
 
     action_sequence.current_tick = 0;
 }
@@ -138,49 +379,209 @@ fn check_target_tick(
 fn update_current_action(
     mut player_action_evr: EventReader<PlayerActionEvent>,
     mut action_sequence: ResMut<ActionSequence>,
+    mut snapshots: ResMut<SequenceSnapshots>,
+    mut record_edit_evw: EventWriter<RecordEditEvent>,
 ) {
+    if player_action_evr.is_empty() {
+        return;
+    }
+
+    // Work out what each player's inputs end up as before touching the sequence, so inputs that
+    // get replaced again right away don't count as a change
+    let current_tick = action_sequence.current_tick;
+    let mut inputs: Vec<(PlayerId, Vec<PlayerAction>)> = Vec::new();
     for player_action_event in player_action_evr.read() {
-        let current_tick = action_sequence.current_tick;
-        action_sequence.sequence[current_tick].0 = player_action_event.action.clone();
+        // Running the sequence makes its inputs again, those are already in it
+        if player_action_event.replayed {
+            continue;
+        }
+
+        let player = player_action_event.player;
+        let index = match inputs.iter().position(|(id, _)| *id == player) {
+            Some(index) => index,
+            None => {
+                let Some(lane) = action_sequence.lane(player) else {
+                    continue;
+                };
+                inputs.push((player, lane.ticks[current_tick].0.clone()));
+                inputs.len() - 1
+            }
+        };
+
+        let actions = &mut inputs[index].1;
+        // A new click replaces the clicks made before it, but gear is still switched
+        if !player_action_event.queued {
+            actions.retain(|action| !action.is_world_click());
+        }
+        actions.push(player_action_event.action.clone());
+    }
+
+    for (player, actions) in inputs {
+        if action_sequence
+            .lane(player)
+            .is_some_and(|lane| lane.ticks[current_tick].0 != actions)
+        {
+            let lane = action_sequence
+                .lane_mut(player)
+                .expect("SHOULD HAVE CHECKED FOR THE LANE");
+            lane.ticks[current_tick].0 = actions;
+            snapshots.invalidate_after(current_tick);
+            record_edit_evw.write(RecordEditEvent);
+        }
     }
 }
 
 fn update_current_modifiers(
     mut action_sequence: ResMut<ActionSequence>,
-    player_modifiers: Res<PlayerModifiers>,
+    mut snapshots: ResMut<SequenceSnapshots>,
+    mut record_edit_evw: EventWriter<RecordEditEvent>,
+    query: Query<(&PlayerId, &PlayerModifiers), With<Player>>,
 ) {
     let current_tick = action_sequence.current_tick;
-    action_sequence.sequence[current_tick].1 = player_modifiers.clone();
+    for (id, modifiers) in query.iter() {
+        if action_sequence
+            .lane(*id)
+            .is_some_and(|lane| lane.ticks[current_tick].1 != *modifiers)
+        {
+            let lane = action_sequence
+                .lane_mut(*id)
+                .expect("SHOULD HAVE CHECKED FOR THE LANE");
+            lane.ticks[current_tick].1 = modifiers.clone();
+            snapshots.invalidate_after(current_tick);
+            record_edit_evw.write(RecordEditEvent);
+        }
+    }
 }
 
-fn reset_sequence(mut action_sequence: ResMut<ActionSequence>) {
-    *action_sequence = ActionSequence::default();
+fn edit_sequence(
+    mut sequence_edit_evr: EventReader<SequenceEditEvent>,
+    mut action_sequence: ResMut<ActionSequence>,
+    mut snapshots: ResMut<SequenceSnapshots>,
+    mut next_state: ResMut<NextState<EditingState>>,
+    mut record_edit_evw: EventWriter<RecordEditEvent>,
+) {
+    for event in sequence_edit_evr.read() {
+        let sequence_len = action_sequence.len();
+        let inputs_len = |action_sequence: &ActionSequence, tick: usize| {
+            action_sequence.selected_lane().ticks[tick].0.len()
+        };
+
+        // First tick whose action is different now, so nothing before it needs to be re-run
+        let first_changed = match *event {
+            SequenceEditEvent::InsertBefore(tick) if tick < sequence_len => {
+                action_sequence.edit_ticks(|ticks| ticks.insert(tick, ticks[tick].clone()));
+                action_sequence.target_tick = tick;
+                tick
+            }
+            SequenceEditEvent::InsertAfter(tick) if tick < sequence_len => {
+                action_sequence.edit_ticks(|ticks| ticks.insert(tick + 1, ticks[tick].clone()));
+                action_sequence.target_tick = tick + 1;
+                tick + 1
+            }
+            // There always has to be at least one tick
+            SequenceEditEvent::Delete(tick) if tick < sequence_len && sequence_len > 1 => {
+                action_sequence.edit_ticks(|ticks| {
+                    ticks.remove(tick);
+                });
+                action_sequence.target_tick = usize::min(tick, sequence_len - 2);
+                tick
+            }
+            SequenceEditEvent::DuplicateRange(start, end) if start <= end && end < sequence_len => {
+                action_sequence.edit_ticks(|ticks| {
+                    let copy = ticks[start..=end].to_vec();
+                    ticks.splice(end + 1..end + 1, copy);
+                });
+                action_sequence.target_tick = end + 1;
+                end + 1
+            }
+            // There always has to be at least one input per tick
+            SequenceEditEvent::RemoveInput(tick, index)
+                if tick < sequence_len
+                    && index < inputs_len(&action_sequence, tick)
+                    && inputs_len(&action_sequence, tick) > 1 =>
+            {
+                action_sequence.selected_lane_mut().ticks[tick]
+                    .0
+                    .remove(index);
+                tick
+            }
+            SequenceEditEvent::MoveInputEarlier(tick, index)
+                if tick < sequence_len
+                    && index > 0
+                    && index < inputs_len(&action_sequence, tick) =>
+            {
+                action_sequence.selected_lane_mut().ticks[tick]
+                    .0
+                    .swap(index - 1, index);
+                tick
+            }
+            SequenceEditEvent::MoveInputLater(tick, index)
+                if tick < sequence_len && index + 1 < inputs_len(&action_sequence, tick) =>
+            {
+                action_sequence.selected_lane_mut().ticks[tick]
+                    .0
+                    .swap(index, index + 1);
+                tick
+            }
+            _ => continue,
+        };
+
+        // Re-run everything from the edit on, which also re-checks for redundant actions
+        snapshots.invalidate_after(first_changed);
+        next_state.set(EditingState::Reconciliation);
+        record_edit_evw.write(RecordEditEvent);
+    }
+}
+
+fn reset_sequence(
+    mut action_sequence: ResMut<ActionSequence>,
+    mut snapshots: ResMut<SequenceSnapshots>,
+    scenario: Res<Scenario>,
+    mut record_edit_evw: EventWriter<RecordEditEvent>,
+) {
+    *action_sequence = ActionSequence::new(scenario.players.iter().map(|spawn| spawn.id));
+    snapshots.clear();
+    record_edit_evw.write(RecordEditEvent);
 }
 
 fn check_redundancies(
     mut action_sequence: ResMut<ActionSequence>,
-    query: Query<&Transform, With<Player>>,
+    mut snapshots: ResMut<SequenceSnapshots>,
+    query: Query<(&PlayerId, &Transform), With<Player>>,
 ) {
-    // Check if the current action is redundant due to changes earlier in the sequence
+    // Check if the current action of each player is redundant due to changes earlier in the
+    // sequence, only the last click on the world matters since it overrides any before it
     let current_tick = action_sequence.current_tick;
-    if let PlayerAction::Move(dest) = action_sequence.sequence[current_tick].0 {
-        let transform = query.single().expect("SHOULD BE ONE PLAYER");
+    for (id, transform) in query.iter() {
+        let Some(lane) = action_sequence.lane(*id) else {
+            continue;
+        };
+        let Some(&PlayerAction::Move(dest)) = prv_last_world_click(&lane.ticks[current_tick].0)
+        else {
+            continue;
+        };
         if dest != transform.translation.truncate() {
-            return;
+            continue;
         }
 
         // Already at target location, so change actions from here on to idle
-        let sequence_len = action_sequence.sequence.len();
-        for i in current_tick..sequence_len {
-            if let PlayerAction::Move(dest2) = action_sequence.sequence[i].0 {
-                if dest != dest2 {
-                    break;
-                }
-
-                action_sequence.sequence[i].0 = PlayerAction::Idle;
-            } else {
+        let lane = action_sequence
+            .lane_mut(*id)
+            .expect("SHOULD HAVE CHECKED FOR THE LANE");
+        for i in current_tick..lane.ticks.len() {
+            let Some(last) = lane.ticks[i]
+                .0
+                .iter_mut()
+                .rfind(|action| action.is_world_click())
+            else {
+                break;
+            };
+            if *last != PlayerAction::Move(dest) {
                 break;
             }
+
+            *last = PlayerAction::Idle;
+            snapshots.invalidate_after(current_tick);
         }
     }
 }
@@ -188,14 +589,16 @@ fn check_redundancies(
 fn send_actions(
     action_sequence: Res<ActionSequence>,
     mut player_action_evw: EventWriter<PlayerActionEvent>,
-    mut player_modifiers: ResMut<PlayerModifiers>,
+    mut modifiers_query: Query<(&PlayerId, &mut PlayerModifiers)>,
 ) {
     let current_tick = action_sequence.current_tick;
-    *player_modifiers = action_sequence.sequence[current_tick].1.clone();
+    for (id, mut modifiers) in modifiers_query.iter_mut() {
+        if let Some(lane) = action_sequence.lane(*id) {
+            *modifiers = lane.ticks[current_tick].1.clone();
+        }
+    }
 
-    player_action_evw.write(PlayerActionEvent {
-        action: action_sequence.sequence[current_tick].0.clone(),
-    });
+    player_action_evw.write_batch(action_sequence.tick_events(current_tick));
 }
 
 fn transition_check(
@@ -215,5 +618,206 @@ fn transition_to_catchup_checks(mut next_state: ResMut<NextState<EditingState>>)
     next_state.set(EditingState::CatchupChecks);
 }
 
-// TODO:
-//   - implement playback mode that goes through the whole sequence in real time
+// Helper to get the last input of a tick that clicks on the world
+fn prv_last_world_click(actions: &[PlayerAction]) -> Option<&PlayerAction> {
+    actions.iter().rfind(|action| action.is_world_click())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn clicks_keep_gear_switches() {
+        let mut world = prv_world();
+        for (action, queued) in [
+            (PlayerAction::Equip(String::from("Ranged")), true),
+            (PlayerAction::Move(Vec2::new(1., 2.)), false),
+        ] {
+            world.send_event(PlayerActionEvent {
+                player: PlayerId(0),
+                action,
+                queued,
+                replayed: false,
+            });
+        }
+        world
+            .run_system_once(update_current_action)
+            .expect("SYSTEM SHOULD RUN");
+
+        assert_eq!(
+            prv_inputs(&world, 0),
+            [
+                PlayerAction::Equip(String::from("Ranged")),
+                PlayerAction::Move(Vec2::new(1., 2.)),
+            ]
+        );
+    }
+
+    #[test]
+    fn replayed_inputs_are_not_recorded() {
+        let mut world = prv_world();
+        world.send_event_batch(PlayerActionEvent::for_tick(
+            PlayerId(0),
+            &[PlayerAction::Move(Vec2::new(1., 2.))],
+        ));
+        world
+            .run_system_once(update_current_action)
+            .expect("SYSTEM SHOULD RUN");
+
+        assert_eq!(prv_inputs(&world, 0), [PlayerAction::Idle]);
+    }
+
+    #[test]
+    fn inserting_ticks() {
+        let mut world = prv_sequence_world(3);
+
+        prv_edit(&mut world, SequenceEditEvent::InsertBefore(1));
+        assert_eq!(prv_origins(&world), [[0., 1., 1., 2.], [0., 1., 1., 2.]]);
+        assert_eq!(world.resource::<ActionSequence>().target_tick, 1);
+        // Runs everything from the edit on again
+        assert!(matches!(
+            world.resource::<NextState<EditingState>>(),
+            NextState::Pending(EditingState::Reconciliation)
+        ));
+
+        prv_edit(&mut world, SequenceEditEvent::InsertAfter(3));
+        assert_eq!(
+            prv_origins(&world),
+            [[0., 1., 1., 2., 2.], [0., 1., 1., 2., 2.]]
+        );
+        assert_eq!(world.resource::<ActionSequence>().target_tick, 4);
+        // Each lane keeps its own inputs
+        assert_eq!(
+            world.resource::<ActionSequence>().lanes[1].ticks[4].0,
+            [PlayerAction::Move(Vec2::new(2., 1.))]
+        );
+
+        // Past the end doesn't do anything
+        prv_edit(&mut world, SequenceEditEvent::InsertAfter(5));
+        assert_eq!(world.resource::<ActionSequence>().len(), 5);
+    }
+
+    #[test]
+    fn deleting_ticks() {
+        let mut world = prv_sequence_world(3);
+
+        prv_edit(&mut world, SequenceEditEvent::Delete(2));
+        assert_eq!(prv_origins(&world), [[0., 1.], [0., 1.]]);
+        // Ends up on the new last tick instead of past the end
+        assert_eq!(world.resource::<ActionSequence>().target_tick, 1);
+
+        prv_edit(&mut world, SequenceEditEvent::Delete(0));
+        assert_eq!(prv_origins(&world), [[1.], [1.]]);
+
+        // There's always at least one tick
+        prv_edit(&mut world, SequenceEditEvent::Delete(0));
+        assert_eq!(prv_origins(&world), [[1.], [1.]]);
+    }
+
+    #[test]
+    fn duplicating_ticks() {
+        let mut world = prv_sequence_world(4);
+
+        prv_edit(&mut world, SequenceEditEvent::DuplicateRange(1, 2));
+        assert_eq!(
+            prv_origins(&world),
+            [[0., 1., 2., 1., 2., 3.], [0., 1., 2., 1., 2., 3.]]
+        );
+        assert_eq!(world.resource::<ActionSequence>().target_tick, 3);
+
+        // Backwards ranges don't do anything
+        prv_edit(&mut world, SequenceEditEvent::DuplicateRange(2, 1));
+        assert_eq!(world.resource::<ActionSequence>().len(), 6);
+    }
+
+    #[test]
+    fn reordering_inputs() {
+        let mut world = prv_sequence_world(2);
+        let equip = PlayerAction::Equip(String::from("Melee"));
+        let click = PlayerAction::Move(Vec2::new(1., 0.));
+        world.resource_mut::<ActionSequence>().lanes[0].ticks[1].0 =
+            vec![click.clone(), equip.clone()];
+
+        prv_edit(&mut world, SequenceEditEvent::MoveInputEarlier(1, 1));
+        assert_eq!(prv_inputs(&world, 1), [equip.clone(), click.clone()]);
+        prv_edit(&mut world, SequenceEditEvent::MoveInputLater(1, 0));
+        assert_eq!(prv_inputs(&world, 1), [click.clone(), equip.clone()]);
+
+        // Nothing before the first or after the last to swap with
+        prv_edit(&mut world, SequenceEditEvent::MoveInputEarlier(1, 0));
+        prv_edit(&mut world, SequenceEditEvent::MoveInputLater(1, 1));
+        assert_eq!(prv_inputs(&world, 1), [click.clone(), equip]);
+
+        // Only the selected player's lane changes
+        assert_eq!(
+            world.resource::<ActionSequence>().lanes[1].ticks[1].0,
+            [PlayerAction::Move(Vec2::new(1., 1.))]
+        );
+    }
+
+    // Helper to make a world with an idle single tick sequence of player 0
+    fn prv_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(ActionSequence::default());
+        world.insert_resource(SequenceSnapshots::default());
+        world.init_resource::<Events<PlayerActionEvent>>();
+        world.init_resource::<Events<RecordEditEvent>>();
+        world.init_resource::<Events<SequenceEditEvent>>();
+        world.init_resource::<NextState<EditingState>>();
+        world
+    }
+
+    // Helper to make a world where two players each move to tile (tick, player) on every tick
+    fn prv_sequence_world(len: usize) -> World {
+        let mut world = prv_world();
+        let mut action_sequence = ActionSequence::new([PlayerId(0), PlayerId(1)]);
+        for lane in action_sequence.lanes.iter_mut() {
+            lane.ticks = (0..len)
+                .map(|tick| {
+                    let dest = Vec2::new(tick as f32, lane.player.0 as f32);
+                    (vec![PlayerAction::Move(dest)], PlayerModifiers::default())
+                })
+                .collect();
+        }
+        world.insert_resource(action_sequence);
+        world
+    }
+
+    // Helper to make a sequence edit
+    fn prv_edit(world: &mut World, event: SequenceEditEvent) {
+        world.send_event(event);
+        world
+            .run_system_once(edit_sequence)
+            .expect("SYSTEM SHOULD RUN");
+        // Every run is a new system that would read the event all over again
+        world.resource_mut::<Events<SequenceEditEvent>>().clear();
+    }
+
+    // Helper to get which tick of the original sequence each tick of every lane came from
+    fn prv_origins(world: &World) -> Vec<Vec<f32>> {
+        world
+            .resource::<ActionSequence>()
+            .lanes
+            .iter()
+            .map(|lane| {
+                lane.ticks
+                    .iter()
+                    .map(|(actions, _)| match actions[..] {
+                        [PlayerAction::Move(dest)] => dest.x,
+                        _ => panic!("TICK SHOULD ONLY MOVE"),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Helper to get the inputs of player 0 on a tick
+    fn prv_inputs(world: &World, tick: usize) -> Vec<PlayerAction> {
+        world.resource::<ActionSequence>().lanes[0].ticks[tick]
+            .0
+            .clone()
+    }
+}
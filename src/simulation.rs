@@ -0,0 +1,258 @@
+use bevy::prelude::*;
+
+use crate::attack::{AttackEvent, AttackPlugin, RecentAttacks, Target};
+use crate::game_ticks::{GameTickEvent, GameTickPlugin};
+use crate::hitpoints::{Hitpoints, HitpointsPlugin, RecentDeaths};
+use crate::movement::{Destination, MovementPlugin};
+use crate::npc::{NpcId, npc_bundle};
+use crate::player::{
+    PlayerAction, PlayerActionEvent, PlayerId, PlayerModifiers, PlayerPlugin, player_bundle,
+};
+use crate::projectile::{Hit, Projectile, ProjectilePlugin, RecentHits};
+use crate::rng::SimulationRng;
+use crate::scenario::Scenario;
+use crate::schedule::{Headless, SchedulePlugin};
+use crate::sequence::{SequenceLane, reset_to_spawn};
+
+/// State of a single entity at the end of a game tick
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityState {
+    pub position: Vec2,
+    pub destination: Option<Vec2>,
+    pub target: Option<Entity>,
+    pub hitpoints: u16,
+}
+
+/// State of every simulated entity at the end of a game tick
+#[derive(Debug, Clone, PartialEq)]
+pub struct TickState {
+    /// In the same order as the players of the scenario
+    pub players: Vec<EntityState>,
+    /// In the same order as the npcs of the scenario
+    pub npcs: Vec<EntityState>,
+    /// Attacks that happened during the tick
+    pub attacks: Vec<AttackEvent>,
+    /// Attacks that hit during the tick, possibly made on an earlier one
+    pub hits: Vec<Hit>,
+    /// Npcs whose hitpoints ran out during the tick
+    pub deaths: Vec<Entity>,
+}
+
+/// A method simulated without a window, rendering or user input.
+///
+/// Only the `Headless` schedule is ever run, so none of the rendering systems the plugins add
+/// get a chance to run.
+pub struct Simulation {
+    app: App,
+    scenario: Scenario,
+    players: Vec<Entity>,
+    npcs: Vec<Entity>,
+}
+
+impl Simulation {
+    /// Set up a simulation with the players and npcs of the scenario at their starting locations
+    pub fn new(scenario: &Scenario) -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            AttackPlugin,
+            GameTickPlugin,
+            HitpointsPlugin,
+            MovementPlugin,
+            PlayerPlugin,
+            ProjectilePlugin,
+            SchedulePlugin,
+        ));
+
+        let world = app.world_mut();
+        world.insert_resource(scenario.collision.clone());
+        world.insert_resource(scenario.gear.clone());
+        let players = scenario
+            .players
+            .iter()
+            .map(|spawn| {
+                let gear = scenario.gear.get_or_unarmed(spawn.starting_gear.as_deref());
+                world.spawn(player_bundle(spawn, &gear)).id()
+            })
+            .collect();
+        let npcs = scenario
+            .npcs
+            .iter()
+            .map(|spawn| world.spawn(npc_bundle(spawn)).id())
+            .collect();
+
+        let mut simulation = Self {
+            app,
+            scenario: scenario.clone(),
+            players,
+            npcs,
+        };
+        simulation.restart();
+
+        simulation
+    }
+
+    /// Put everything back the way the scenario starts off, to run lanes from the start again
+    pub fn restart(&mut self) {
+        let world = self.app.world_mut();
+
+        let projectiles: Vec<Entity> = world
+            .query_filtered::<Entity, With<Projectile>>()
+            .iter(world)
+            .collect();
+        for projectile in projectiles {
+            world.despawn(projectile);
+        }
+
+        let players: Vec<(Entity, PlayerId)> = self
+            .players
+            .iter()
+            .map(|player| {
+                let id = world
+                    .get::<PlayerId>(*player)
+                    .expect("PLAYER SHOULD HAVE AN ID");
+                (*player, *id)
+            })
+            .collect();
+        let npcs: Vec<(Entity, NpcId)> = self
+            .npcs
+            .iter()
+            .map(|npc| {
+                let id = world.get::<NpcId>(*npc).expect("NPC SHOULD HAVE AN ID");
+                (*npc, *id)
+            })
+            .collect();
+        reset_to_spawn(&mut world.commands(), &self.scenario, &players, &npcs);
+        world.flush();
+
+        world.insert_resource(SimulationRng::new(self.scenario.seed));
+        world.resource_mut::<RecentAttacks>().0.clear();
+        world.resource_mut::<RecentHits>().0.clear();
+        world.resource_mut::<RecentDeaths>().0.clear();
+    }
+
+    /// The simulated player entities, in the same order as the players of the scenario
+    pub fn players(&self) -> &[Entity] {
+        &self.players
+    }
+
+    /// The simulated player entity with the given id
+    pub fn player(&self, id: PlayerId) -> Option<Entity> {
+        self.players
+            .iter()
+            .copied()
+            .find(|player| self.app.world().get::<PlayerId>(*player) == Some(&id))
+    }
+
+    /// The simulated npc entities, in the same order as the npcs of the scenario.
+    ///
+    /// `PlayerAction::Attack` has to use these to target an npc.
+    pub fn npcs(&self) -> &[Entity] {
+        &self.npcs
+    }
+
+    /// The simulated npc entity with the given id
+    pub fn npc(&self, id: NpcId) -> Option<Entity> {
+        self.npcs
+            .iter()
+            .copied()
+            .find(|npc| self.app.world().get::<NpcId>(*npc) == Some(&id))
+    }
+
+    /// Run the lanes of every player, returning the state at the end of every tick.
+    ///
+    /// Runs for as long as the longest lane, players whose lane is shorter are idle once it runs
+    /// out like they are in `SequenceLane::idle`.
+    pub fn run(&mut self, lanes: &[SequenceLane]) -> Vec<TickState> {
+        let len = lanes.iter().map(|lane| lane.ticks.len()).max().unwrap_or(0);
+        let idle = (vec![PlayerAction::Idle], PlayerModifiers::default());
+        (0..len)
+            .map(|tick| {
+                let inputs: Vec<_> = lanes
+                    .iter()
+                    .map(|lane| {
+                        let (actions, modifiers) = lane.ticks.get(tick).unwrap_or(&idle);
+                        (lane.player, actions.as_slice(), modifiers)
+                    })
+                    .collect();
+                self.step(&inputs)
+            })
+            .collect()
+    }
+
+    /// Run a single game tick with the given inputs of each player in order, returning the state
+    /// at the end of it
+    pub fn step(&mut self, inputs: &[(PlayerId, &[PlayerAction], &PlayerModifiers)]) -> TickState {
+        for (player, actions, modifiers) in inputs.iter() {
+            let Some(entity) = self.player(*player) else {
+                continue;
+            };
+
+            let world = self.app.world_mut();
+            world.entity_mut(entity).insert((*modifiers).clone());
+            world.send_event_batch(PlayerActionEvent::for_tick(*player, actions));
+        }
+
+        let world = self.app.world_mut();
+
+        world.run_schedule(Headless);
+
+        // Nothing else is going to update the event buffers, so do it here to not keep every
+        // event ever sent around
+        world.resource_mut::<Events<PlayerActionEvent>>().update();
+        world.resource_mut::<Events<GameTickEvent>>().update();
+        world.resource_mut::<Events<AttackEvent>>().update();
+
+        self.state()
+    }
+
+    /// Current state of every simulated entity
+    pub fn state(&self) -> TickState {
+        let world = self.app.world();
+
+        TickState {
+            players: self
+                .players
+                .iter()
+                .map(|player| prv_entity_state(world, *player))
+                .collect(),
+            npcs: self
+                .npcs
+                .iter()
+                .map(|npc| prv_entity_state(world, *npc))
+                .collect(),
+            attacks: world.resource::<RecentAttacks>().0.clone(),
+            hits: world.resource::<RecentHits>().0.clone(),
+            deaths: world.resource::<RecentDeaths>().0.clone(),
+        }
+    }
+}
+
+/// Simulate the lanes of every player from the scenario's starting setup.
+///
+/// Any `PlayerAction::Attack` targets have to be the npc entities of a `Simulation`, so use that
+/// directly for sequences that attack.
+pub fn simulate(scenario: &Scenario, lanes: &[SequenceLane]) -> Vec<TickState> {
+    Simulation::new(scenario).run(lanes)
+}
+
+/// Tick the given npc died on out of the simulated ticks, if it died at all
+pub fn death_tick(ticks: &[TickState], npc: Entity) -> Option<usize> {
+    ticks.iter().position(|tick| tick.deaths.contains(&npc))
+}
+
+fn prv_entity_state(world: &World, entity: Entity) -> EntityState {
+    let entity_ref = world.entity(entity);
+
+    EntityState {
+        position: entity_ref
+            .get::<Transform>()
+            .map(|transform| transform.translation.truncate())
+            .unwrap_or_default(),
+        destination: entity_ref.get::<Destination>().map(|dest| dest.0),
+        target: entity_ref.get::<Target>().map(|target| target.0),
+        hitpoints: entity_ref
+            .get::<Hitpoints>()
+            .map(|hitpoints| hitpoints.current)
+            .unwrap_or_default(),
+    }
+}
@@ -0,0 +1,303 @@
+use bevy::prelude::*;
+
+use crate::attack::{
+    AttackEvent, AttackProjectile, AttackRange, AttackRng, AttackSpeed, AttackStatus, AttackStyle,
+    Cooldown, MaxHit, RecentAttacks, Target, TargetUnderBehavior,
+};
+use crate::combat::AttackRoll;
+use crate::gear::Equipped;
+use crate::hitpoints::{Dead, Hitpoints, RecentDeaths};
+use crate::movement::{Destination, MovementOrder, NpcMovementOrders, Path};
+use crate::npc::Npc;
+use crate::player::Player;
+use crate::projectile::{Hit, Projectile, RecentHits};
+use crate::rng::SimulationRng;
+use crate::sequence::ActionSequence;
+
+/// State of a single entity at the start of a tick, before that tick's action is taken
+#[derive(Debug, Clone)]
+pub struct EntitySnapshot {
+    entity: Entity,
+    transform: Transform,
+    destination: Option<Vec2>,
+    path: Option<Path>,
+    target: Option<Entity>,
+    cooldown: Option<u8>,
+    movement_order: Option<MovementOrder>,
+    npc_movement_orders: Option<NpcMovementOrders>,
+    under_behavior: Option<TargetUnderBehavior>,
+    attack_status: Option<AttackStatus>,
+    attack_range: u8,
+    attack_speed: u8,
+    attack_style: Option<AttackStyle>,
+    max_hit: Option<u16>,
+    projectile: Option<AttackProjectile>,
+    attack_roll: Option<u32>,
+    attack_rng: Option<AttackRng>,
+    equipped: Option<Equipped>,
+    hitpoints: Option<Hitpoints>,
+    dead: bool,
+}
+
+/// Everything needed to pick the sequence back up at a tick without re-running the ticks before it
+#[derive(Debug, Clone)]
+pub struct TickSnapshot {
+    entities: Vec<EntitySnapshot>,
+    rng: SimulationRng,
+    /// Attacks that happened on this tick
+    attacks: Vec<AttackEvent>,
+    /// Attacks that hit on this tick
+    hits: Vec<Hit>,
+    /// Npcs that died on this tick
+    deaths: Vec<Entity>,
+    /// Projectiles still on their way, along with where they are
+    projectiles: Vec<(Projectile, Transform)>,
+}
+
+/// Snapshots of every tick of the sequence that has been run since it was last edited, indexed by
+/// tick
+#[derive(Resource, Debug, Default)]
+pub struct SequenceSnapshots(Vec<TickSnapshot>);
+
+impl SequenceSnapshots {
+    /// Latest snapshot at or before the given tick, along with the tick it's for
+    pub fn latest(&self, tick: usize) -> Option<(usize, &TickSnapshot)> {
+        let tick = usize::min(tick, self.0.len().checked_sub(1)?);
+
+        Some((tick, &self.0[tick]))
+    }
+
+    /// Attacks that happened on the given tick, if it has been run
+    pub fn attacks(&self, tick: usize) -> &[AttackEvent] {
+        self.0
+            .get(tick)
+            .map(|snapshot| snapshot.attacks.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Attacks that hit on the given tick, if it has been run
+    pub fn hits(&self, tick: usize) -> &[Hit] {
+        self.0
+            .get(tick)
+            .map(|snapshot| snapshot.hits.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Npcs that died on the given tick, if it has been run
+    pub fn deaths(&self, tick: usize) -> &[Entity] {
+        self.0
+            .get(tick)
+            .map(|snapshot| snapshot.deaths.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Throw away the snapshots of every tick after the given one, since they could play out
+    /// differently now
+    pub fn invalidate_after(&mut self, tick: usize) {
+        self.0.truncate(tick + 1);
+    }
+
+    /// Throw away every snapshot
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+impl TickSnapshot {
+    /// Put every entity and all randomness back to how they were at the snapshot. Any projectiles
+    /// currently around need to be despawned first.
+    pub fn restore(
+        &self,
+        commands: &mut Commands,
+        rng: &mut SimulationRng,
+        recent: (&mut RecentAttacks, &mut RecentHits, &mut RecentDeaths),
+    ) {
+        for snapshot in self.entities.iter() {
+            // Anything despawned since doesn't need restoring
+            let Ok(mut entity) = commands.get_entity(snapshot.entity) else {
+                continue;
+            };
+
+            // Anything the entity didn't have at the snapshot is removed, since it could have
+            // picked it up since
+            entity.insert((
+                snapshot.transform,
+                AttackRange(snapshot.attack_range),
+                AttackSpeed(snapshot.attack_speed),
+            ));
+            match snapshot.cooldown {
+                Some(cooldown) => entity.insert(Cooldown(cooldown)),
+                None => entity.try_remove::<Cooldown>(),
+            };
+            match snapshot.destination {
+                Some(destination) => entity.insert(Destination(destination)),
+                None => entity.try_remove::<Destination>(),
+            };
+            match &snapshot.path {
+                Some(path) => entity.insert(path.clone()),
+                None => entity.try_remove::<Path>(),
+            };
+            match snapshot.target {
+                Some(target) => entity.insert(Target(target)),
+                None => entity.try_remove::<Target>(),
+            };
+            match &snapshot.movement_order {
+                Some(order) => entity.insert(order.clone()),
+                None => entity.try_remove::<MovementOrder>(),
+            };
+            match &snapshot.npc_movement_orders {
+                Some(orders) => entity.insert(orders.clone()),
+                None => entity.try_remove::<NpcMovementOrders>(),
+            };
+            match &snapshot.under_behavior {
+                Some(behavior) => entity.insert(behavior.clone()),
+                None => entity.try_remove::<TargetUnderBehavior>(),
+            };
+            match snapshot.attack_status {
+                Some(status) => entity.insert(status),
+                None => entity.try_remove::<AttackStatus>(),
+            };
+            match snapshot.attack_style {
+                Some(style) => entity.insert(style),
+                None => entity.try_remove::<AttackStyle>(),
+            };
+            match snapshot.max_hit {
+                Some(max_hit) => entity.insert(MaxHit(max_hit)),
+                None => entity.try_remove::<MaxHit>(),
+            };
+            match snapshot.projectile {
+                Some(projectile) => entity.insert(projectile),
+                None => entity.try_remove::<AttackProjectile>(),
+            };
+            match snapshot.attack_roll {
+                Some(attack_roll) => entity.insert(AttackRoll(attack_roll)),
+                None => entity.try_remove::<AttackRoll>(),
+            };
+            match &snapshot.attack_rng {
+                Some(attack_rng) => entity.insert(attack_rng.clone()),
+                None => entity.try_remove::<AttackRng>(),
+            };
+            match &snapshot.equipped {
+                Some(equipped) => entity.insert(equipped.clone()),
+                None => entity.try_remove::<Equipped>(),
+            };
+            match snapshot.hitpoints {
+                Some(hitpoints) => entity.insert(hitpoints),
+                None => entity.try_remove::<Hitpoints>(),
+            };
+            match snapshot.dead {
+                true => entity.insert(Dead),
+                false => entity.try_remove::<Dead>(),
+            };
+        }
+
+        for (projectile, transform) in self.projectiles.iter() {
+            commands.spawn((projectile.clone(), *transform));
+        }
+
+        let (recent_attacks, recent_hits, recent_deaths) = recent;
+        *rng = self.rng.clone();
+        recent_attacks.0 = self.attacks.clone();
+        recent_hits.0 = self.hits.clone();
+        recent_deaths.0 = self.deaths.clone();
+    }
+}
+
+/// Snapshot the current tick of the sequence if it doesn't have one yet
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn record_snapshot(
+    action_sequence: Res<ActionSequence>,
+    mut snapshots: ResMut<SequenceSnapshots>,
+    rng: Res<SimulationRng>,
+    recent_attacks: Res<RecentAttacks>,
+    recent_hits: Res<RecentHits>,
+    recent_deaths: Res<RecentDeaths>,
+    projectile_query: Query<(&Projectile, &Transform)>,
+    query: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&Destination>,
+            Option<&Path>,
+            Option<&Target>,
+            Option<&Cooldown>,
+            Option<&MovementOrder>,
+            Option<&NpcMovementOrders>,
+            Option<&TargetUnderBehavior>,
+            Option<&AttackStatus>,
+            &AttackRange,
+            &AttackSpeed,
+            Option<&AttackStyle>,
+            (
+                Option<&MaxHit>,
+                Option<&AttackProjectile>,
+                Option<&AttackRoll>,
+                Option<&AttackRng>,
+                Option<&Equipped>,
+                Option<&Hitpoints>,
+                Has<Dead>,
+            ),
+        ),
+        Or<(With<Player>, With<Npc>)>,
+    >,
+) {
+    // Ticks are always run in order, so anything earlier already has a snapshot
+    if snapshots.0.len() != action_sequence.current_tick {
+        return;
+    }
+
+    let entities = query
+        .iter()
+        .map(
+            |(
+                entity,
+                transform,
+                destination,
+                path,
+                target,
+                cooldown,
+                movement_order,
+                npc_movement_orders,
+                under_behavior,
+                attack_status,
+                attack_range,
+                attack_speed,
+                attack_style,
+                (max_hit, projectile, attack_roll, attack_rng, equipped, hitpoints, dead),
+            )| EntitySnapshot {
+                entity,
+                transform: *transform,
+                destination: destination.map(|destination| destination.0),
+                path: path.cloned(),
+                target: target.map(|target| target.0),
+                cooldown: cooldown.map(|cooldown| cooldown.0),
+                movement_order: movement_order.cloned(),
+                npc_movement_orders: npc_movement_orders.cloned(),
+                under_behavior: under_behavior.cloned(),
+                attack_status: attack_status.copied(),
+                attack_range: attack_range.0,
+                attack_speed: attack_speed.0,
+                attack_style: attack_style.copied(),
+                max_hit: max_hit.map(|max_hit| max_hit.0),
+                projectile: projectile.copied(),
+                attack_roll: attack_roll.map(|attack_roll| attack_roll.0),
+                attack_rng: attack_rng.cloned(),
+                equipped: equipped.cloned(),
+                hitpoints: hitpoints.copied(),
+                dead,
+            },
+        )
+        .collect();
+
+    snapshots.0.push(TickSnapshot {
+        entities,
+        rng: rng.clone(),
+        attacks: recent_attacks.0.clone(),
+        hits: recent_hits.0.clone(),
+        deaths: recent_deaths.0.clone(),
+        projectiles: projectile_query
+            .iter()
+            .map(|(projectile, transform)| (projectile.clone(), *transform))
+            .collect(),
+    });
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::attack::{AttackStyle, ProjectileKind};
+
+pub mod gear_editor;
+pub mod npc_editor;
+pub mod scenario_editor;
+
+/// Highest max hit that can be set up in the UI
+pub const MAX_HIT: u16 = 200;
+
+/// Most hitpoints anything can be set up with in the UI
+pub const MAX_HITPOINTS: u16 = 10000;
+
+/// Largest attack, strength or defence bonus that can be set up in the UI, either way
+pub const MAX_BONUS: i16 = 1000;
+
+/// Longest fixed hit delay an attack can be set up with in the UI, in game ticks
+const MAX_HIT_DELAY: u8 = 10;
+
+/// Edit a tile position, returns whether it was changed
+pub fn position_edit(ui: &mut egui::Ui, label: &str, position: &mut Vec2) -> bool {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let x = ui.add(
+            egui::DragValue::new(&mut position.x)
+                .speed(0.1)
+                .prefix("x: "),
+        );
+        let y = ui.add(
+            egui::DragValue::new(&mut position.y)
+                .speed(0.1)
+                .prefix("y: "),
+        );
+
+        // Positions are always on a tile
+        *position = position.round();
+
+        x.changed() || y.changed()
+    })
+    .inner
+}
+
+/// Pick what a ranged or magic attack sends at its target, if not the usual one for its style
+pub fn projectile_edit(
+    ui: &mut egui::Ui,
+    id: impl std::hash::Hash,
+    style: AttackStyle,
+    projectile: &mut Option<ProjectileKind>,
+) {
+    // Melee always hits right away
+    let Some(usual) = style.projectile() else {
+        *projectile = None;
+        return;
+    };
+
+    // A fixed delay keeps its delay when switching away and back
+    let delay = match projectile {
+        Some(ProjectileKind::Fixed(delay)) => *delay,
+        _ => 1,
+    };
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(projectile.unwrap_or(usual).to_string())
+        .show_ui(ui, |ui| {
+            ui.selectable_value(projectile, None, format!("{usual} (usual)"));
+            for kind in [
+                ProjectileKind::Arrow,
+                ProjectileKind::Thrown,
+                ProjectileKind::Spell,
+                ProjectileKind::Fixed(delay),
+            ] {
+                ui.selectable_value(projectile, Some(kind), kind.to_string());
+            }
+        })
+        .response
+        .on_hover_text("Decides how long the attack takes to hit");
+    if let Some(ProjectileKind::Fixed(delay)) = projectile {
+        ui.add(
+            egui::DragValue::new(delay)
+                .range(0..=MAX_HIT_DELAY)
+                .suffix(" ticks"),
+        );
+    }
+}
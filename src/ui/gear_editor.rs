@@ -0,0 +1,306 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::{EguiContextPass, EguiContexts, egui};
+
+use crate::attack::{AttackStyle, MaxHit};
+use crate::combat::CombatStats;
+use crate::gear::{Equipped, GearSetup, MAX_ATTACK_RANGE, WeaponData, WeaponStyle};
+use crate::player::{Player, PlayerAction, PlayerActionEvent, PlayerId, PlayerModifiers};
+use crate::scenario::Scenario;
+use crate::sequence::ActionSequence;
+use crate::state::ToolState;
+use crate::ui::{MAX_BONUS, MAX_HIT, projectile_edit};
+
+/// Highest combat level a player can have, boosts included
+const MAX_PLAYER_LEVEL: u8 = 125;
+
+pub struct GearEditorPlugin;
+
+impl Plugin for GearEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(EguiContextPass, draw_player_ui);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_player_ui(
+    mut contexts: EguiContexts,
+    state: Res<State<ToolState>>,
+    mut scenario: ResMut<Scenario>,
+    mut action_sequence: ResMut<ActionSequence>,
+    weapon_data: Res<WeaponData>,
+    mut player_action_evw: EventWriter<PlayerActionEvent>,
+    mut player_query: Query<(&PlayerId, &mut PlayerModifiers, &Equipped, &MaxHit), With<Player>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    let window = window_query.single().expect("SHOULD BE ONE WINDOW");
+
+    egui::Window::new("Player Modifiers")
+        .collapsible(false)
+        .resizable(false)
+        .movable(false)
+        .current_pos((window.width(), 0.))
+        .max_width(350.)
+        .show(contexts.ctx_mut(), |ui| {
+            // The sequence decides the modifiers and gear during playback
+            if state.get() == &ToolState::Playback {
+                ui.disable();
+            }
+
+            // Everything here is for the player being edited in the timeline
+            let selected_player = action_sequence.selected_player;
+            let Some((_, mut player_modifiers, equipped, max_hit)) = player_query
+                .iter_mut()
+                .find(|(id, ..)| **id == selected_player)
+            else {
+                return;
+            };
+
+            // Edit copies so the scenario is only marked as changed when something was edited
+            let mut gear = scenario.gear.clone();
+            let mut players = scenario.players.clone();
+
+            if let Some(spawn) = players.iter_mut().find(|spawn| spawn.id == selected_player) {
+                ui.label(&spawn.name);
+                prv_combat_stats_edit(ui, &mut spawn.stats);
+            }
+
+            ui.checkbox(&mut player_modifiers.run, "Run");
+
+            ui.separator();
+
+            ui.label(format!("Gear: {}, max hit {}", equipped.0, max_hit.0));
+
+            let mut removed = None;
+            for (i, setup) in gear.setups.iter_mut().enumerate() {
+                let old_name = setup.name.clone();
+                let equipped_in_sequence = action_sequence.lanes.iter().any(|lane| {
+                    lane.ticks.iter().any(|(actions, _)| {
+                        actions.contains(&PlayerAction::Equip(old_name.clone()))
+                    })
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut setup.name).desired_width(100.));
+                    // Equipping is its own input on top of any clicks already made this tick
+                    if ui.button("Equip").clicked() {
+                        player_action_evw.write(PlayerActionEvent {
+                            player: selected_player,
+                            action: PlayerAction::Equip(old_name.clone()),
+                            queued: true,
+                            replayed: false,
+                        });
+                    }
+                    // Can't remove gear the sequence still switches to
+                    if ui
+                        .add_enabled(!equipped_in_sequence, egui::Button::new("🗙"))
+                        .on_disabled_hover_text("Equipped in the sequence")
+                        .clicked()
+                    {
+                        removed = Some(i);
+                    }
+                });
+                prv_gear_setup_edit(ui, i, setup, &weapon_data);
+
+                // Names have to stay unique since that's how the sequence refers to them, and
+                // renaming carries over to everywhere the setup is used
+                if setup.name != old_name {
+                    if scenario.gear.get(&setup.name).is_some() {
+                        setup.name = old_name;
+                    } else {
+                        prv_rename_gear(&mut action_sequence, &old_name, &setup.name);
+                        for spawn in players.iter_mut() {
+                            if spawn.starting_gear.as_ref() == Some(&old_name) {
+                                spawn.starting_gear = Some(setup.name.clone());
+                            }
+                        }
+                    }
+                }
+            }
+            if let Some(i) = removed {
+                let setup = gear.setups.remove(i);
+                for spawn in players.iter_mut() {
+                    if spawn.starting_gear.as_ref() == Some(&setup.name) {
+                        spawn.starting_gear = None;
+                    }
+                }
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Add Gear").clicked() {
+                    gear.setups.push(GearSetup {
+                        name: gear.unused_name(),
+                        ..GearSetup::unarmed()
+                    });
+                }
+
+                let Some(spawn) = players.iter_mut().find(|spawn| spawn.id == selected_player)
+                else {
+                    return;
+                };
+                let mut starting = spawn.starting_gear.clone();
+                egui::ComboBox::from_label("Starting gear")
+                    .selected_text(
+                        starting
+                            .clone()
+                            .unwrap_or_else(|| GearSetup::unarmed().name),
+                    )
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut starting, None, GearSetup::unarmed().name);
+                        for setup in gear.setups.iter() {
+                            ui.selectable_value(
+                                &mut starting,
+                                Some(setup.name.clone()),
+                                &setup.name,
+                            );
+                        }
+                    });
+                spawn.starting_gear = starting;
+            });
+
+            if gear != scenario.gear {
+                scenario.gear = gear;
+            }
+            if players != scenario.players {
+                scenario.players = players;
+            }
+        });
+}
+
+// Helper to rename a gear setup everywhere the sequence equips it
+fn prv_rename_gear(action_sequence: &mut ActionSequence, old_name: &str, new_name: &str) {
+    for lane in action_sequence.lanes.iter_mut() {
+        for (actions, _) in lane.ticks.iter_mut() {
+            for action in actions.iter_mut() {
+                if let PlayerAction::Equip(name) = action
+                    && name == old_name
+                {
+                    *name = new_name.to_string();
+                }
+            }
+        }
+    }
+}
+
+// Helper to pick the weapon and style of a gear setup, or enter its stats by hand for custom gear
+fn prv_gear_setup_edit(
+    ui: &mut egui::Ui,
+    id: usize,
+    setup: &mut GearSetup,
+    weapon_data: &WeaponData,
+) {
+    let weapon = setup
+        .weapon
+        .as_ref()
+        .and_then(|pick| weapon_data.get(&pick.weapon));
+
+    ui.horizontal(|ui| {
+        let mut picked = weapon.map(|weapon| weapon.name.clone());
+        egui::ComboBox::from_id_salt(("gear weapon", id))
+            .selected_text(picked.as_deref().unwrap_or("Custom"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut picked, None, "Custom");
+                for weapon in weapon_data.0.iter() {
+                    ui.selectable_value(&mut picked, Some(weapon.name.clone()), &weapon.name);
+                }
+            });
+        if picked != weapon.map(|weapon| weapon.name.clone()) {
+            match picked.as_deref().and_then(|name| weapon_data.get(name)) {
+                Some(weapon) => {
+                    let style = weapon
+                        .styles
+                        .first()
+                        .copied()
+                        .unwrap_or(WeaponStyle::Accurate);
+                    setup.pick_weapon(weapon, style);
+                }
+                // Start off custom gear with the stats it already had
+                None => setup.weapon = None,
+            }
+            return;
+        }
+
+        if let (Some(weapon), Some(pick)) = (weapon, &setup.weapon) {
+            let mut style = pick.style;
+            egui::ComboBox::from_id_salt(("gear weapon style", id))
+                .selected_text(style.to_string())
+                .show_ui(ui, |ui| {
+                    for weapon_style in weapon.styles.iter() {
+                        ui.selectable_value(&mut style, *weapon_style, weapon_style.to_string());
+                    }
+                });
+            if style != pick.style {
+                setup.pick_weapon(weapon, style);
+            }
+            ui.label(format!("{} ticks, {} tiles", setup.speed, setup.range));
+            return;
+        }
+
+        // Anything not in the weapon data has its stats entered by hand
+        setup.weapon = None;
+        egui::ComboBox::from_id_salt(("gear attack style", id))
+            .selected_text(setup.style.to_string())
+            .show_ui(ui, |ui| {
+                for style in [AttackStyle::Melee, AttackStyle::Ranged, AttackStyle::Magic] {
+                    ui.selectable_value(&mut setup.style, style, style.to_string());
+                }
+            });
+        ui.add(
+            egui::DragValue::new(&mut setup.speed)
+                .range(1..=10)
+                .suffix(" ticks"),
+        );
+        ui.add(
+            egui::DragValue::new(&mut setup.range)
+                .range(1..=MAX_ATTACK_RANGE)
+                .suffix(" tiles"),
+        );
+        projectile_edit(
+            ui,
+            ("gear projectile", id),
+            setup.style,
+            &mut setup.projectile,
+        );
+    });
+    // Bonuses of the whole setup, only the ones for the style it attacks with
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::DragValue::new(&mut setup.bonuses.attack)
+                .range(-MAX_BONUS..=MAX_BONUS)
+                .prefix("Attack: "),
+        );
+        let strength = match setup.style {
+            AttackStyle::Melee => "Strength: ",
+            AttackStyle::Ranged => "Ranged strength: ",
+            AttackStyle::Magic => "Magic damage: ",
+        };
+        let mut strength_value = egui::DragValue::new(&mut setup.bonuses.strength)
+            .range(-MAX_BONUS..=MAX_BONUS)
+            .prefix(strength);
+        if setup.style == AttackStyle::Magic {
+            strength_value = strength_value.suffix("%");
+        }
+        ui.add(strength_value);
+        if setup.style == AttackStyle::Magic {
+            ui.add(
+                egui::DragValue::new(&mut setup.bonuses.spell_max_hit)
+                    .range(0..=MAX_HIT)
+                    .prefix("Spell max hit: "),
+            );
+        }
+    });
+}
+
+// Helper to edit the combat levels of a player
+fn prv_combat_stats_edit(ui: &mut egui::Ui, stats: &mut CombatStats) {
+    ui.horizontal(|ui| {
+        for (level, name) in [
+            (&mut stats.attack, "Attack"),
+            (&mut stats.strength, "Strength"),
+            (&mut stats.ranged, "Ranged"),
+            (&mut stats.magic, "Magic"),
+        ] {
+            ui.label(name);
+            ui.add(egui::DragValue::new(level).range(1..=MAX_PLAYER_LEVEL));
+        }
+    });
+}
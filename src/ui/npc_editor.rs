@@ -0,0 +1,365 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::{EguiContextPass, EguiContexts, egui};
+
+use crate::attack::{AttackCondition, AttackStyle, NpcAttack, TargetUnderBehavior};
+use crate::combat::NpcDefence;
+use crate::gear::MAX_ATTACK_RANGE;
+use crate::movement::MovementType;
+use crate::npc::{Npc, NpcEntities, NpcId, Size};
+use crate::npc_library::{NpcLibrary, NpcLibraryEvent};
+use crate::player::{PlayerAction, PlayerId};
+use crate::scenario::{NpcSpawn, PlayerSpawn, Scenario};
+use crate::sequence::ActionSequence;
+use crate::state::ToolState;
+use crate::ui::{MAX_BONUS, MAX_HIT, MAX_HITPOINTS, position_edit, projectile_edit};
+
+/// Largest npc that can be set up in the UI
+const MAX_NPC_SIZE: u8 = 10;
+
+/// Fastest npc that can be set up in the UI, in tiles per game tick
+const MAX_NPC_SPEED: u8 = 4;
+
+/// Highest index an npc can have in the game
+const MAX_NPC_INDEX: u16 = 32767;
+
+/// Highest defence or magic level that can be set up for an npc in the UI
+const MAX_NPC_LEVEL: u16 = 1000;
+
+/// Color of the outline around the npc selected in the UI
+const SELECTED_NPC_COLOR: Color = Color::srgb(1., 1., 0.);
+
+/// Npc picked in the UI to be edited
+#[derive(Resource, Debug, Default)]
+struct SelectedNpc(Option<NpcId>);
+
+pub struct NpcEditorPlugin;
+
+impl Plugin for NpcEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SelectedNpc::default())
+            .add_systems(
+                EguiContextPass,
+                draw_npcs_ui.run_if(in_state(ToolState::Editing)),
+            )
+            .add_systems(
+                Update,
+                highlight_selected_npc.run_if(in_state(ToolState::Editing)),
+            );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_npcs_ui(
+    mut contexts: EguiContexts,
+    mut scenario: ResMut<Scenario>,
+    mut selected_npc: ResMut<SelectedNpc>,
+    action_sequence: Res<ActionSequence>,
+    npc_entities: Res<NpcEntities>,
+    npc_library: Res<NpcLibrary>,
+    mut npc_library_evw: EventWriter<NpcLibraryEvent>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    // Name of the npc definition picked to be added
+    mut picked_npc: Local<Option<String>>,
+) {
+    let window = window_query.single().expect("SHOULD BE ONE WINDOW");
+
+    egui::Window::new("NPCs")
+        .collapsible(false)
+        .resizable(false)
+        .movable(false)
+        .current_pos((window.width(), window.height() / 2.))
+        .max_width(350.)
+        .show(contexts.ctx_mut(), |ui| {
+            // Edit a copy so the scenario is only marked as changed when something was edited
+            let mut npcs = scenario.npcs.clone();
+            let mut removed = None;
+            for (i, spawn) in npcs.iter().enumerate() {
+                let attacked_in_sequence = npc_entities.get(spawn.id).is_some_and(|npc| {
+                    action_sequence.lanes.iter().any(|lane| {
+                        lane.ticks
+                            .iter()
+                            .any(|(actions, _)| actions.contains(&PlayerAction::Attack(npc)))
+                    })
+                });
+
+                ui.horizontal(|ui| {
+                    let selected = selected_npc.0 == Some(spawn.id);
+                    if ui.selectable_label(selected, &spawn.name).clicked() {
+                        selected_npc.0 = if selected { None } else { Some(spawn.id) };
+                    }
+                    // Can't remove an npc the sequence still attacks
+                    if ui
+                        .add_enabled(!attacked_in_sequence, egui::Button::new("🗙"))
+                        .on_disabled_hover_text("Attacked in the sequence")
+                        .clicked()
+                    {
+                        removed = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = removed {
+                npcs.remove(i);
+            }
+
+            if let Some(spawn) = npcs
+                .iter_mut()
+                .find(|spawn| Some(spawn.id) == selected_npc.0)
+            {
+                ui.separator();
+                prv_npc_spawn_edit(ui, spawn, &scenario.players);
+            }
+
+            ui.separator();
+
+            // Stick with the picked npc for adding several of it in a row
+            if picked_npc
+                .as_deref()
+                .is_none_or(|name| npc_library.get(name).is_none())
+            {
+                *picked_npc = npc_library
+                    .definitions
+                    .first()
+                    .map(|definition| definition.name.clone());
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Add NPC").clicked() {
+                    // Close to the player so it's easy to find, then it can be dragged into place
+                    let id = scenario.unused_npc_id();
+                    npcs.push(NpcSpawn {
+                        id,
+                        position: scenario.new_npc_position(),
+                        target: scenario.players.first().map(|spawn| spawn.id),
+                        index: scenario.unused_npc_index(),
+                        ..default()
+                    });
+                    selected_npc.0 = Some(id);
+                }
+
+                egui::ComboBox::from_id_salt("npc library")
+                    .selected_text(picked_npc.as_deref().unwrap_or("No npcs"))
+                    .show_ui(ui, |ui| {
+                        for definition in npc_library.definitions.iter() {
+                            ui.selectable_value(
+                                &mut *picked_npc,
+                                Some(definition.name.clone()),
+                                &definition.name,
+                            );
+                        }
+                    });
+                if let Some(name) = picked_npc.as_ref()
+                    && ui.button("Add").clicked()
+                {
+                    npc_library_evw.write(NpcLibraryEvent::Spawn(name.clone()));
+                }
+                if ui
+                    .button("Reload")
+                    .on_hover_text("Load the npc definitions again")
+                    .clicked()
+                {
+                    npc_library_evw.write(NpcLibraryEvent::Reload);
+                }
+            });
+            for error in npc_library.errors.iter() {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+
+            if npcs != scenario.npcs {
+                scenario.npcs = npcs;
+            }
+        });
+}
+
+fn highlight_selected_npc(
+    mut gizmos: Gizmos,
+    selected_npc: Res<SelectedNpc>,
+    npc_entities: Res<NpcEntities>,
+    npc_query: Query<(&Transform, &Size), With<Npc>>,
+) {
+    let Some(npc) = selected_npc.0.and_then(|id| npc_entities.get(id)) else {
+        return;
+    };
+    // Removed from the scenario
+    let Ok((transform, size)) = npc_query.get(npc) else {
+        return;
+    };
+
+    // Just outside of the npc's own outline
+    gizmos.rect_2d(
+        Isometry2d::from_translation(transform.translation.truncate() + (size.0 as f32 / 2. - 0.5)),
+        Vec2::splat(size.0 as f32 + 0.2),
+        SELECTED_NPC_COLOR,
+    );
+}
+
+// Helper to edit the defensive stats of an npc
+fn prv_npc_defence_edit(ui: &mut egui::Ui, defence: &mut NpcDefence) {
+    ui.horizontal(|ui| {
+        ui.label("Defence");
+        ui.add(egui::DragValue::new(&mut defence.defence_level).range(1..=MAX_NPC_LEVEL));
+        ui.label("Magic");
+        ui.add(egui::DragValue::new(&mut defence.magic_level).range(1..=MAX_NPC_LEVEL));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Defence bonuses");
+        for (bonus, style) in [
+            (&mut defence.melee_bonus, AttackStyle::Melee),
+            (&mut defence.ranged_bonus, AttackStyle::Ranged),
+            (&mut defence.magic_bonus, AttackStyle::Magic),
+        ] {
+            ui.add(
+                egui::DragValue::new(bonus)
+                    .range(-MAX_BONUS..=MAX_BONUS)
+                    .prefix(format!("{style}: ")),
+            );
+        }
+    })
+    .response
+    .on_hover_text("Melee is the bonus against the type of melee attack it gets hit with");
+}
+
+// Helper to edit everything about an npc
+fn prv_npc_spawn_edit(ui: &mut egui::Ui, spawn: &mut NpcSpawn, players: &[PlayerSpawn]) {
+    ui.horizontal(|ui| {
+        ui.label("Name");
+        ui.add(egui::TextEdit::singleline(&mut spawn.name).desired_width(150.));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Size");
+        ui.add(egui::DragValue::new(&mut spawn.size).range(1..=MAX_NPC_SIZE));
+        ui.label("Speed");
+        ui.add(
+            egui::DragValue::new(&mut spawn.speed)
+                .range(1..=MAX_NPC_SPEED)
+                .suffix(" tiles"),
+        );
+        ui.label("Index");
+        ui.add(egui::DragValue::new(&mut spawn.index).range(0..=MAX_NPC_INDEX))
+            .on_hover_text("Npcs with lower indices are processed first");
+    });
+    ui.horizontal(|ui| {
+        ui.label("Hitpoints");
+        ui.add(egui::DragValue::new(&mut spawn.hitpoints).range(1..=MAX_HITPOINTS));
+    });
+    prv_npc_defence_edit(ui, &mut spawn.defence);
+    position_edit(ui, "Position", &mut spawn.position);
+
+    egui::ComboBox::from_label("Movement")
+        .selected_text(spawn.movement_type.to_string())
+        .show_ui(ui, |ui| {
+            for movement_type in [
+                MovementType::Dumb,
+                MovementType::CardinalFirst,
+                MovementType::DiagonalFirst,
+                MovementType::Pathfinding,
+            ] {
+                let label = movement_type.to_string();
+                ui.selectable_value(&mut spawn.movement_type, movement_type, label);
+            }
+        });
+    egui::ComboBox::from_label("Under target")
+        .selected_text(spawn.under_behavior.to_string())
+        .show_ui(ui, |ui| {
+            for behavior in [
+                TargetUnderBehavior::RandomCardinal,
+                TargetUnderBehavior::MoveOut,
+                TargetUnderBehavior::StayStill,
+            ] {
+                let label = behavior.to_string();
+                ui.selectable_value(&mut spawn.under_behavior, behavior, label);
+            }
+        });
+
+    let target_name = |target: Option<PlayerId>| {
+        target
+            .and_then(|id| players.iter().find(|player| player.id == id))
+            .map_or("Nobody", |player| player.name.as_str())
+    };
+    egui::ComboBox::from_label("Target")
+        .selected_text(target_name(spawn.target))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut spawn.target, None, target_name(None));
+            for player in players.iter() {
+                ui.selectable_value(&mut spawn.target, Some(player.id), &player.name);
+            }
+        })
+        .response
+        .on_hover_text("Without a target it waits until a player attacks it");
+
+    ui.label("Attacks, the first one whose condition holds is used");
+    let mut removed = None;
+    for (i, attack) in spawn.attacks.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            prv_npc_attack_edit(ui, i, attack);
+            // Always needs something to attack with
+            if ui.add_enabled(i > 0, egui::Button::new("🗙")).clicked() {
+                removed = Some(i);
+            }
+        });
+    }
+    if let Some(i) = removed {
+        spawn.attacks.remove(i);
+    }
+    if ui.button("Add Attack").clicked() {
+        spawn.attacks.push(NpcAttack::default());
+    }
+}
+
+// Helper to edit a single attack of an npc
+fn prv_npc_attack_edit(ui: &mut egui::Ui, id: usize, attack: &mut NpcAttack) {
+    egui::ComboBox::from_id_salt(("npc attack style", id))
+        .selected_text(attack.style.to_string())
+        .show_ui(ui, |ui| {
+            for style in [AttackStyle::Melee, AttackStyle::Ranged, AttackStyle::Magic] {
+                ui.selectable_value(&mut attack.style, style, style.to_string());
+            }
+        });
+    ui.add(
+        egui::DragValue::new(&mut attack.speed)
+            .range(1..=10)
+            .suffix(" ticks"),
+    );
+    ui.add(
+        egui::DragValue::new(&mut attack.range)
+            .range(1..=MAX_ATTACK_RANGE)
+            .suffix(" tiles"),
+    );
+    ui.add(
+        egui::DragValue::new(&mut attack.max_hit)
+            .range(0..=MAX_HIT)
+            .prefix("max "),
+    );
+    projectile_edit(
+        ui,
+        ("npc attack projectile", id),
+        attack.style,
+        &mut attack.projectile,
+    );
+
+    // Distance conditions keep their distance when switching between them
+    let distance = match attack.condition {
+        AttackCondition::Within(distance) | AttackCondition::Beyond(distance) => distance,
+        _ => 1,
+    };
+    egui::ComboBox::from_id_salt(("npc attack condition", id))
+        .selected_text(attack.condition.to_string())
+        .show_ui(ui, |ui| {
+            for condition in [
+                AttackCondition::Always,
+                AttackCondition::Adjacent,
+                AttackCondition::NotAdjacent,
+                AttackCondition::Within(distance),
+                AttackCondition::Beyond(distance),
+                AttackCondition::Chance(50),
+            ] {
+                ui.selectable_value(&mut attack.condition, condition, condition.to_string());
+            }
+        });
+    if let AttackCondition::Within(distance) | AttackCondition::Beyond(distance) =
+        &mut attack.condition
+    {
+        ui.add(egui::DragValue::new(distance).range(1..=MAX_ATTACK_RANGE));
+    }
+    if let AttackCondition::Chance(percent) = &mut attack.condition {
+        ui.add(egui::DragValue::new(percent).range(0..=100).suffix("%"));
+    }
+}
@@ -0,0 +1,144 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::{EguiContextPass, EguiContexts, egui};
+
+use crate::scenario::{PlayerSpawn, Scenario, ScenarioTool};
+use crate::sequence::ActionSequence;
+use crate::state::ToolState;
+use crate::ui::{MAX_HITPOINTS, position_edit};
+
+/// Highest PID a player can have in the game
+const MAX_PID: u16 = 2047;
+
+pub struct ScenarioEditorPlugin;
+
+impl Plugin for ScenarioEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            EguiContextPass,
+            draw_scenario_ui.run_if(in_state(ToolState::Editing)),
+        );
+    }
+}
+
+fn draw_scenario_ui(
+    mut contexts: EguiContexts,
+    mut scenario: ResMut<Scenario>,
+    mut scenario_tool: ResMut<ScenarioTool>,
+    mut action_sequence: ResMut<ActionSequence>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    let window = window_query.single().expect("SHOULD BE ONE WINDOW");
+
+    egui::Window::new("Scenario")
+        .collapsible(false)
+        .resizable(false)
+        .movable(false)
+        .current_pos((0., window.height()))
+        .show(contexts.ctx_mut(), |ui| {
+            let &(mut desired_tool) = scenario_tool.as_ref();
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut desired_tool, ScenarioTool::Actions, "Actions");
+                ui.selectable_value(&mut desired_tool, ScenarioTool::PlaceStart, "Place Start");
+                ui.selectable_value(
+                    &mut desired_tool,
+                    ScenarioTool::PaintBlocked,
+                    "Paint Blocked",
+                );
+            });
+            if desired_tool != *scenario_tool {
+                *scenario_tool = desired_tool;
+                if desired_tool == ScenarioTool::PlaceStart {
+                    // Starting positions are only shown at the start
+                    action_sequence.target_tick = 0;
+                }
+            }
+
+            ui.separator();
+
+            // Edit copies so the scenario is only marked as changed when something was edited
+            let mut seed = scenario.seed;
+            ui.horizontal(|ui| {
+                ui.label("Seed");
+                ui.add(egui::DragValue::new(&mut seed));
+                if ui.button("Randomize").clicked() {
+                    seed = rand::random();
+                }
+            });
+            if seed != scenario.seed {
+                scenario.seed = seed;
+            }
+
+            ui.separator();
+
+            let mut players = scenario.players.clone();
+            let mut npcs = scenario.npcs.clone();
+            let mut removed = None;
+            for (i, spawn) in players.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut spawn.name).desired_width(100.));
+                    position_edit(ui, "Start", &mut spawn.position);
+                    ui.add(
+                        egui::DragValue::new(&mut spawn.pid)
+                            .range(0..=MAX_PID)
+                            .prefix("PID: "),
+                    )
+                    .on_hover_text("Players with lower PIDs are processed first");
+                    ui.add(
+                        egui::DragValue::new(&mut spawn.hitpoints)
+                            .range(1..=MAX_HITPOINTS)
+                            .prefix("HP: "),
+                    );
+                    // There's always someone to make the inputs
+                    if ui
+                        .add_enabled(scenario.players.len() > 1, egui::Button::new("🗙"))
+                        .clicked()
+                    {
+                        removed = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = removed {
+                let spawn = players.remove(i);
+                action_sequence.remove_lane(spawn.id);
+                // Npcs that went after the player wait to be attacked instead
+                for npc in npcs.iter_mut() {
+                    if npc.target == Some(spawn.id) {
+                        npc.target = None;
+                    }
+                }
+            }
+            let mut reverse_pids = false;
+            ui.horizontal(|ui| {
+                if ui.button("Add Player").clicked() {
+                    let id = scenario.unused_player_id();
+                    // Next to the first player so it's easy to find, then it can be dragged into
+                    // place
+                    let position = players
+                        .first()
+                        .map_or(Vec2::ZERO, |spawn| spawn.position + Vec2::X);
+                    players.push(PlayerSpawn {
+                        id,
+                        name: format!("Player {}", id.0 + 1),
+                        position,
+                        pid: scenario.unused_pid(),
+                        ..default()
+                    });
+                    action_sequence.add_lane(id);
+                }
+                reverse_pids = ui
+                    .add_enabled(players.len() > 1, egui::Button::new("Reverse PIDs"))
+                    .on_hover_text("See how the method plays out with the other PID outcome")
+                    .clicked();
+            });
+
+            if players != scenario.players {
+                scenario.players = players;
+            }
+            if reverse_pids {
+                scenario.reverse_pids();
+            }
+            if npcs != scenario.npcs {
+                scenario.npcs = npcs;
+            }
+        });
+}
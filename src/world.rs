@@ -1,15 +1,163 @@
+use std::collections::BTreeSet;
+
 use bevy::{platform::collections::HashMap, prelude::*};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::rng::SimulationRng;
+use crate::scenario::Scenario;
 
 /// Default base tile color
 const DEFAULT_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
 
+/// Fixed point scale used when casting a line of sight, the same as the game
+const LINE_OF_SIGHT_SCALE: i32 = 1 << 16;
+
+/// Color of the outline of blocked tiles
+const BLOCKED_COLOR: Color = Color::srgb(0.05, 0.05, 0.05);
+
 /// Coordinate, mostly created for hashing purposes
-#[derive(Debug, Hash, PartialEq, Eq, Default, Clone, Copy)]
-struct Coord {
+#[derive(
+    Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Default, Clone, Copy, Serialize, Deserialize,
+)]
+pub struct Coord {
     x: isize,
     y: isize,
 }
 
+impl From<Vec2> for Coord {
+    fn from(tile: Vec2) -> Self {
+        Self {
+            x: tile.x as isize,
+            y: tile.y as isize,
+        }
+    }
+}
+
+impl From<Coord> for Vec2 {
+    fn from(coord: Coord) -> Self {
+        Vec2::new(coord.x as f32, coord.y as f32)
+    }
+}
+
+/// Which tiles can't be walked on
+#[derive(Resource, Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollisionMap {
+    blocked: BTreeSet<Coord>,
+}
+
+impl CollisionMap {
+    pub fn is_blocked(&self, tile: Vec2) -> bool {
+        self.blocked.contains(&Coord::from(tile))
+    }
+
+    pub fn set_blocked(&mut self, tile: Vec2, blocked: bool) {
+        if blocked {
+            self.blocked.insert(Coord::from(tile));
+        } else {
+            self.blocked.remove(&Coord::from(tile));
+        }
+    }
+
+    pub fn blocked_tiles(&self) -> impl Iterator<Item = Vec2> {
+        self.blocked.iter().map(|coord| Vec2::from(*coord))
+    }
+
+    /// Whether an entity of the given size can stand with its SW tile on the given tile
+    pub fn is_footprint_free(&self, sw_tile: Vec2, size: u8) -> bool {
+        (0..size).all(|dy| {
+            (0..size).all(|dx| !self.is_blocked(sw_tile + Vec2::new(dx as f32, dy as f32)))
+        })
+    }
+
+    /// Whether an entity of the given size can take a single step, each component of the step
+    /// being -1, 0 or 1.
+    ///
+    /// Like the game, a diagonal step is blocked when either of the cardinal steps making it up is.
+    pub fn can_step(&self, sw_tile: Vec2, size: u8, step: Vec2) -> bool {
+        if step.x != 0. && step.y != 0. {
+            self.is_footprint_free(sw_tile + Vec2::new(step.x, 0.), size)
+                && self.is_footprint_free(sw_tile + Vec2::new(0., step.y), size)
+                && self.is_footprint_free(sw_tile + step, size)
+        } else {
+            self.is_footprint_free(sw_tile + step, size)
+        }
+    }
+
+    /// Whether a projectile can travel between two entities with the given SW tiles and sizes.
+    ///
+    /// Like the game, the line is cast between the closest tiles of the two entities.
+    pub fn has_line_of_sight(
+        &self,
+        sw_tile: Vec2,
+        size: u8,
+        target_sw_tile: Vec2,
+        target_size: u8,
+    ) -> bool {
+        let start = IVec2::new(
+            prv_closest_coordinate(sw_tile.x as i32, target_sw_tile.x as i32, size),
+            prv_closest_coordinate(sw_tile.y as i32, target_sw_tile.y as i32, size),
+        );
+        let end = IVec2::new(
+            prv_closest_coordinate(target_sw_tile.x as i32, start.x, target_size),
+            prv_closest_coordinate(target_sw_tile.y as i32, start.y, target_size),
+        );
+
+        self.prv_cast_line_of_sight(start, end)
+    }
+
+    // Helper to cast a line between two tiles the same way the game does, walking along the
+    // longer axis and checking every tile the line crosses
+    fn prv_cast_line_of_sight(&self, start: IVec2, end: IVec2) -> bool {
+        let delta = end - start;
+        let blocked = |x: i32, y: i32| self.is_blocked(Vec2::new(x as f32, y as f32));
+
+        if delta.x.abs() > delta.y.abs() {
+            let offset_x = delta.x.signum();
+            let offset_y = if delta.y >= 0 { 0 } else { -1 };
+            let slope = delta.y * LINE_OF_SIGHT_SCALE / delta.x.abs();
+            let mut scaled_y = start.y * LINE_OF_SIGHT_SCALE + LINE_OF_SIGHT_SCALE / 2 + offset_y;
+
+            let mut x = start.x;
+            while x != end.x {
+                x += offset_x;
+                let y = scaled_y >> 16;
+                if blocked(x, y) {
+                    return false;
+                }
+
+                scaled_y += slope;
+                let next_y = scaled_y >> 16;
+                if next_y != y && blocked(x, next_y) {
+                    return false;
+                }
+            }
+        } else if delta.y != 0 {
+            let offset_y = delta.y.signum();
+            let offset_x = if delta.x >= 0 { 0 } else { -1 };
+            let slope = delta.x * LINE_OF_SIGHT_SCALE / delta.y.abs();
+            let mut scaled_x = start.x * LINE_OF_SIGHT_SCALE + LINE_OF_SIGHT_SCALE / 2 + offset_x;
+
+            let mut y = start.y;
+            while y != end.y {
+                y += offset_y;
+                let x = scaled_x >> 16;
+                if blocked(x, y) {
+                    return false;
+                }
+
+                scaled_x += slope;
+                let next_x = scaled_x >> 16;
+                if next_x != x && blocked(next_x, y) {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
 /// Set of all created tile entities
 #[derive(Resource, Debug, Default)]
 struct WorldTiles {
@@ -30,8 +178,13 @@ impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(ClearColor(Color::srgb(0., 0., 0.)))
             .insert_resource(WorldTiles::default())
+            .insert_resource(CollisionMap::default())
             .add_systems(PostStartup, create_grid)
-            .add_systems(Update, update_grid);
+            .add_systems(
+                PreUpdate,
+                sync_collision_map.run_if(resource_changed::<Scenario>),
+            )
+            .add_systems(Update, (update_grid, draw_blocked_tiles));
     }
 }
 
@@ -40,6 +193,7 @@ fn create_grid(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut world_tiles: ResMut<WorldTiles>,
+    rng: Res<SimulationRng>,
     query: Query<(&Projection, &Transform)>,
 ) -> Result {
     let (Projection::Orthographic(ortho), transform) = query.single()? else {
@@ -54,6 +208,7 @@ fn create_grid(
                 &mut commands,
                 &mut meshes,
                 &mut materials,
+                &rng,
                 DEFAULT_COLOR,
                 Coord { x, y },
             );
@@ -72,6 +227,7 @@ fn update_grid(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut world_tiles: ResMut<WorldTiles>,
+    rng: Res<SimulationRng>,
     query: Query<(&Projection, &Transform), Changed<Projection>>,
 ) {
     let Ok((Projection::Orthographic(ortho), transform)) = query.single() else {
@@ -90,6 +246,7 @@ fn update_grid(
                 &mut commands,
                 &mut meshes,
                 &mut materials,
+                &rng,
                 DEFAULT_COLOR,
                 Coord { x, y },
             );
@@ -110,14 +267,44 @@ fn update_grid(
     world_tiles.extents = (neg_extent, pos_extent);
 }
 
+fn sync_collision_map(scenario: Res<Scenario>, mut collision_map: ResMut<CollisionMap>) {
+    // Done before anything runs in Update so that re-running the sequence uses the new map
+    if *collision_map != scenario.collision {
+        *collision_map = scenario.collision.clone();
+    }
+}
+
+fn draw_blocked_tiles(mut gizmos: Gizmos, collision_map: Res<CollisionMap>) {
+    for tile in collision_map.blocked_tiles() {
+        gizmos.rect_2d(
+            Isometry2d::from_translation(tile),
+            Vec2::splat(0.9),
+            BLOCKED_COLOR,
+        );
+        gizmos.line_2d(tile - 0.45, tile + 0.45, BLOCKED_COLOR);
+        gizmos.line_2d(
+            tile + Vec2::new(-0.45, 0.45),
+            tile + Vec2::new(0.45, -0.45),
+            BLOCKED_COLOR,
+        );
+    }
+}
+
 fn prv_add_tile(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<ColorMaterial>>,
+    rng: &SimulationRng,
     base_color: Color,
     coord: Coord,
 ) -> Entity {
-    let offset = rand::random_range(-0.025..=0.025);
+    // Every tile gets its own rng so its shade doesn't depend on when it was created
+    let key = [
+        (coord.x as i64).to_le_bytes(),
+        (coord.y as i64).to_le_bytes(),
+    ]
+    .concat();
+    let offset = rng.fork(&key).random_range(-0.025..=0.025);
 
     let mut color = base_color.to_srgba();
     color.red += offset;
@@ -148,3 +335,59 @@ fn prv_get_extents(area: &Rect, transform: &Transform) -> (Coord, Coord) {
 
     (neg_extent, pos_extent)
 }
+
+// Helper to find the coordinate of an entity closest to another coordinate along one axis
+fn prv_closest_coordinate(sw_coordinate: i32, other: i32, size: u8) -> i32 {
+    if sw_coordinate >= other {
+        sw_coordinate
+    } else if sw_coordinate + size as i32 - 1 <= other {
+        sw_coordinate + size as i32 - 1
+    } else {
+        other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_of_sight_on_open_ground() {
+        let collision_map = CollisionMap::default();
+        assert!(collision_map.has_line_of_sight(Vec2::ZERO, 1, Vec2::new(7., 3.), 1));
+        assert!(collision_map.has_line_of_sight(Vec2::ZERO, 1, Vec2::new(-4., -6.), 3));
+    }
+
+    #[test]
+    fn blocked_tiles_block_line_of_sight() {
+        let mut collision_map = CollisionMap::default();
+        collision_map.set_blocked(Vec2::new(2., 0.), true);
+
+        assert!(!collision_map.has_line_of_sight(Vec2::ZERO, 1, Vec2::new(4., 0.), 1));
+        assert!(!collision_map.has_line_of_sight(Vec2::new(1., 0.), 1, Vec2::new(3., 0.), 1));
+        // Lines that only pass close by it
+        assert!(collision_map.has_line_of_sight(Vec2::ZERO, 1, Vec2::new(4., 2.), 1));
+        assert!(collision_map.has_line_of_sight(Vec2::new(0., 2.), 1, Vec2::new(4., 2.), 1));
+    }
+
+    #[test]
+    fn diagonal_line_of_sight() {
+        let mut collision_map = CollisionMap::default();
+        collision_map.set_blocked(Vec2::new(1., 1.), true);
+
+        assert!(!collision_map.has_line_of_sight(Vec2::ZERO, 1, Vec2::new(3., 3.), 1));
+        assert!(collision_map.has_line_of_sight(Vec2::ZERO, 1, Vec2::new(3., -3.), 1));
+    }
+
+    #[test]
+    fn line_of_sight_from_closest_tiles_of_large_entities() {
+        let mut collision_map = CollisionMap::default();
+        collision_map.set_blocked(Vec2::new(2., 2.), true);
+
+        // The size 3 target's closest tile is level with the player, so the blocked tile is off
+        // to the side of the line
+        assert!(collision_map.has_line_of_sight(Vec2::ZERO, 1, Vec2::new(3., -1.), 3));
+        // A size 1 target on the far corner of where the large one was is behind it
+        assert!(!collision_map.has_line_of_sight(Vec2::ZERO, 1, Vec2::new(5., 5.), 1));
+    }
+}
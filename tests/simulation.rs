@@ -0,0 +1,285 @@
+use bevy::prelude::*;
+use osrs_method_creator::attack::{AttackStyle, NpcAttack, ProjectileKind};
+use osrs_method_creator::combat::GearBonuses;
+use osrs_method_creator::gear::GearSetup;
+use osrs_method_creator::player::{PlayerAction, PlayerId};
+use osrs_method_creator::scenario::{PlayerSpawn, Scenario};
+use osrs_method_creator::sequence::SequenceLane;
+use osrs_method_creator::simulation::{Simulation, death_tick, simulate};
+
+// Helper to make a scenario with a single size 1 npc on the origin
+fn prv_scenario(npc_attack: NpcAttack) -> Scenario {
+    let mut scenario = Scenario::default();
+    scenario.npcs[0].size = 1;
+    scenario.npcs[0].position = Vec2::ZERO;
+    scenario.npcs[0].attacks = vec![npc_attack];
+    scenario
+}
+
+#[test]
+fn player_walks_around_walls() {
+    let mut scenario = Scenario::default();
+    scenario.npcs.clear();
+    for y in -3..=3 {
+        scenario
+            .collision
+            .set_blocked(Vec2::new(2., y as f32), true);
+    }
+
+    // Clicking the same tile every tick keeps walking the same path
+    let mut lane = SequenceLane::idle(PlayerId(0), 6);
+    for (actions, _) in lane.ticks.iter_mut() {
+        *actions = vec![PlayerAction::Move(Vec2::new(4., 0.))];
+    }
+    let ticks = simulate(&scenario, &[lane]);
+
+    let positions: Vec<Vec2> = ticks.iter().map(|tick| tick.players[0].position).collect();
+    assert!(
+        positions
+            .iter()
+            .all(|position| !scenario.collision.is_blocked(*position))
+    );
+    assert_eq!(
+        positions,
+        [
+            Vec2::new(0., -2.),
+            Vec2::new(1., -4.),
+            Vec2::new(3., -4.),
+            Vec2::new(3., -2.),
+            Vec2::new(4., 0.),
+            Vec2::new(4., 0.),
+        ]
+    );
+    assert_eq!(
+        ticks.last().and_then(|tick| tick.players[0].destination),
+        None
+    );
+}
+
+#[test]
+fn hit_delays_follow_the_projectile() {
+    for (projectile, delay) in [
+        (None, 2),
+        (Some(ProjectileKind::Thrown), 1),
+        (Some(ProjectileKind::Fixed(4)), 4),
+    ] {
+        let mut scenario = prv_scenario(NpcAttack {
+            style: AttackStyle::Ranged,
+            range: 10,
+            projectile,
+            ..default()
+        });
+        scenario.players[0].position = Vec2::new(5., 0.);
+
+        let ticks = simulate(&scenario, &[SequenceLane::idle(PlayerId(0), 8)]);
+        let attack_tick = ticks
+            .iter()
+            .position(|tick| !tick.attacks.is_empty())
+            .expect("NPC SHOULD ATTACK");
+        assert_eq!(ticks[attack_tick].attacks[0].hit_delay, delay);
+
+        let hit_tick = ticks
+            .iter()
+            .position(|tick| !tick.hits.is_empty())
+            .expect("ATTACK SHOULD HIT");
+        assert_eq!(hit_tick, attack_tick + delay as usize, "{projectile:?}");
+    }
+}
+
+#[test]
+fn lower_pid_attacks_first() {
+    let mut scenario = prv_scenario(NpcAttack::default());
+    // Waits to be attacked, then goes after whoever did it first
+    scenario.npcs[0].target = None;
+    scenario.players[0].position = Vec2::new(-1., 0.);
+    scenario.players.push(PlayerSpawn {
+        id: PlayerId(1),
+        name: String::from("Player 2"),
+        position: Vec2::new(1., 0.),
+        pid: 1,
+        ..default()
+    });
+
+    for (first, second) in [(0, 1), (1, 0)] {
+        let mut simulation = Simulation::new(&scenario);
+        let npc = simulation.npcs()[0];
+        let lanes: Vec<SequenceLane> = scenario
+            .players
+            .iter()
+            .map(|spawn| {
+                let mut lane = SequenceLane::idle(spawn.id, 2);
+                lane.ticks[0].0 = vec![PlayerAction::Attack(npc)];
+                lane
+            })
+            .collect();
+        let ticks = simulation.run(&lanes);
+
+        let players = simulation.players();
+        let attackers: Vec<Entity> = ticks[0]
+            .attacks
+            .iter()
+            .map(|attack| attack.attacker)
+            .collect();
+        assert_eq!(attackers, [players[first], players[second]]);
+        assert_eq!(ticks[0].npcs[0].target, Some(players[first]));
+
+        scenario.reverse_pids();
+    }
+}
+
+#[test]
+fn shorter_lanes_are_padded_with_idle_ticks() {
+    let mut scenario = Scenario::default();
+    scenario.npcs.clear();
+    scenario.players.push(PlayerSpawn {
+        id: PlayerId(1),
+        name: String::from("Player 2"),
+        position: Vec2::new(0., 5.),
+        pid: 1,
+        ..default()
+    });
+    let walking_lane = |player, len| {
+        let mut lane = SequenceLane::idle(player, len);
+        for (actions, _) in lane.ticks.iter_mut() {
+            *actions = vec![PlayerAction::Move(Vec2::new(20., 0.))];
+        }
+        lane
+    };
+
+    let ticks = simulate(
+        &scenario,
+        &[walking_lane(PlayerId(0), 2), walking_lane(PlayerId(1), 5)],
+    );
+
+    assert_eq!(ticks.len(), 5);
+    // Stops where it was once its lane runs out, the other keeps going
+    let stopped = ticks[1].players[0].position;
+    assert_ne!(stopped, scenario.players[0].position);
+    for tick in ticks[2..].iter() {
+        assert_eq!(tick.players[0].position, stopped);
+        assert_eq!(tick.players[0].destination, None);
+    }
+    assert_ne!(ticks[4].players[1].position, ticks[1].players[1].position);
+}
+
+#[test]
+fn restarting_runs_like_a_fresh_simulation() {
+    let mut scenario = prv_scenario(NpcAttack {
+        max_hit: 5,
+        ..default()
+    });
+    scenario.npcs[0].hitpoints = 3;
+    scenario.players[0].position = Vec2::new(4., 0.);
+
+    let mut simulation = Simulation::new(&scenario);
+    let npc = simulation.npcs()[0];
+    let mut lane = SequenceLane::idle(PlayerId(0), 12);
+    lane.ticks[0].0 = vec![PlayerAction::Attack(npc)];
+    lane.ticks[8].0 = vec![PlayerAction::Move(Vec2::new(6., 3.))];
+    let fresh = simulation.run(&[lane.clone()]);
+    // Ends up with the npc dead and the player walking off somewhere
+    assert!(fresh.iter().any(|tick| !tick.deaths.is_empty()));
+
+    simulation.restart();
+    let rerun = simulation.run(&[lane]);
+
+    assert_eq!(rerun, fresh);
+}
+
+#[test]
+fn dead_npcs_stop_the_fight() {
+    let mut scenario = prv_scenario(NpcAttack::default());
+    scenario.npcs[0].target = None;
+    scenario.npcs[0].hitpoints = 40;
+    for (name, style, speed, range) in [
+        ("Melee", AttackStyle::Melee, 4, 1),
+        ("Ranged", AttackStyle::Ranged, 3, 10),
+    ] {
+        scenario.gear.setups.push(GearSetup {
+            name: String::from(name),
+            weapon: None,
+            style,
+            speed,
+            range,
+            projectile: None,
+            bonuses: GearBonuses {
+                attack: 100,
+                strength: 20,
+                spell_max_hit: 0,
+            },
+        });
+    }
+    scenario.players[0].position = Vec2::new(-1., 0.);
+    scenario.players[0].starting_gear = Some(String::from("Melee"));
+    // Far enough that there's always an arrow on its way
+    scenario.players.push(PlayerSpawn {
+        id: PlayerId(1),
+        name: String::from("Player 2"),
+        position: Vec2::new(10., 0.),
+        starting_gear: Some(String::from("Ranged")),
+        pid: 1,
+        ..default()
+    });
+
+    let mut simulation = Simulation::new(&scenario);
+    let npc = simulation.npcs()[0];
+    let lanes: Vec<SequenceLane> = scenario
+        .players
+        .iter()
+        .map(|spawn| {
+            // Attacking every tick, clicking a dead npc doesn't do anything
+            let mut lane = SequenceLane::idle(spawn.id, 20);
+            for (actions, _) in lane.ticks.iter_mut() {
+                *actions = vec![PlayerAction::Attack(npc)];
+            }
+            lane
+        })
+        .collect();
+    let ticks = simulation.run(&lanes);
+
+    let death = death_tick(&ticks, npc).expect("NPC SHOULD DIE");
+    assert_eq!(ticks[death].npcs[0].hitpoints, 0);
+    // Was fighting back until then
+    assert!(ticks[death - 1].npcs[0].target.is_some());
+    for tick in ticks[death..].iter() {
+        assert!(tick.players.iter().all(|player| player.target.is_none()));
+        assert_eq!(tick.npcs[0].target, None);
+    }
+    assert!(
+        ticks[death + 1..]
+            .iter()
+            .all(|tick| tick.attacks.is_empty())
+    );
+
+    // Arrows already on their way still land, without doing anything
+    let late_hits = ticks[death + 1..]
+        .iter()
+        .flat_map(|tick| tick.hits.iter())
+        .filter(|hit| hit.target == npc && hit.damage > 0)
+        .count();
+    assert!(late_hits > 0);
+    assert!(ticks[death + 1..].iter().all(|tick| tick.deaths.is_empty()));
+    assert!(
+        ticks[death..]
+            .iter()
+            .all(|tick| tick.npcs[0].hitpoints == 0)
+    );
+}
+
+#[test]
+fn attacks_wait_for_the_cooldown() {
+    for speed in [2, 4, 5] {
+        let mut scenario = prv_scenario(NpcAttack { speed, ..default() });
+        scenario.players[0].position = Vec2::new(1., 0.);
+
+        let ticks = simulate(&scenario, &[SequenceLane::idle(PlayerId(0), 12)]);
+        let attack_ticks: Vec<usize> = ticks
+            .iter()
+            .enumerate()
+            .filter(|(_, tick)| !tick.attacks.is_empty())
+            .map(|(tick, _)| tick)
+            .collect();
+        let expected: Vec<usize> = (0..12).step_by(speed as usize).collect();
+        assert_eq!(attack_ticks, expected, "speed {speed}");
+    }
+}